@@ -0,0 +1,37 @@
+use anyhow::Context as AnyhowContext;
+use std::path::Path;
+use std::process::Stdio;
+
+pub enum Verdict {
+    Clean,
+    /// Scanner output explaining what was found
+    Flagged(String),
+}
+
+/// Runs `command` with the archive path appended, e.g. `clamscan --no-summary`.
+/// Exit code 0 means clean and 1 means flagged like clamscan, anything else is an error
+pub async fn check(command: &str, archive: &Path) -> anyhow::Result<Verdict> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().context("Archive scan command is empty")?;
+
+    let output = tokio::process::Command::new(program)
+        .args(parts)
+        .arg(archive)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context(format!("Failed to run {}", program))?;
+
+    match output.status.code() {
+        Some(0) => Ok(Verdict::Clean),
+        Some(1) => Ok(Verdict::Flagged(
+            String::from_utf8_lossy(&output.stdout).trim().to_owned(),
+        )),
+        _ => anyhow::bail!(
+            "{} failed with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+    }
+}
@@ -0,0 +1,815 @@
+use crate::config::MAX_TOTAL_SIZE_BYTES;
+use crate::probe;
+use crate::thumbnail;
+use crate::transcode::{self, TranscodeSettings};
+use anyhow::Context as AnyhowContext;
+use poise::serenity_prelude::{self as serenity, Attachment, AttachmentId, ChannelId, MessageId};
+use std::collections::HashSet;
+use std::io::{Read, Seek, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::{NamedTempFile, TempDir};
+
+/// Skipped clips listed by name before the rest are only counted
+const MAX_LISTED_SKIPPED: usize = 10;
+
+pub struct Submission {
+    pub attachment: Attachment,
+    /// Thread and message the attachment was posted in, re-fetched when the attachment url expires
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    /// Folder of the archive the clip is written to, the root when unset
+    pub folder: Option<String>,
+    /// Move the clip is named after, the archive's move when unset
+    pub move_name: Option<String>,
+    pub username: String,
+    pub text: String,
+}
+
+/// Compression of the files in the archive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, poise::ChoiceParameter)]
+pub enum Compression {
+    #[default]
+    #[name = "deflate"]
+    Deflate,
+    /// Zstandard, smaller and faster but older unzip tools can't extract it
+    #[name = "zstd"]
+    Zstd,
+    /// No compression, videos barely compress anyway
+    #[name = "stored"]
+    Stored,
+}
+
+impl Compression {
+    fn method(self) -> zip::CompressionMethod {
+        match self {
+            Compression::Deflate => zip::CompressionMethod::Deflated,
+            Compression::Zstd => zip::CompressionMethod::Zstd,
+            Compression::Stored => zip::CompressionMethod::Stored,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("expected deflate, zstd or stored")]
+pub struct InvalidCompression;
+
+impl FromStr for Compression {
+    type Err = InvalidCompression;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "deflate" => Ok(Self::Deflate),
+            "zstd" => Ok(Self::Zstd),
+            "stored" => Ok(Self::Stored),
+            _ => Err(InvalidCompression),
+        }
+    }
+}
+
+impl ArchiveOptions {
+    /// How the file `file_name` is written to the archive
+    fn file_options(&self, file_name: &str) -> zip::write::SimpleFileOptions {
+        let stored = Path::new(file_name)
+            .extension()
+            .and_then(std::ffi::OsStr::to_str)
+            .is_some_and(|extension| {
+                self.stored_extensions
+                    .iter()
+                    .any(|stored| stored.eq_ignore_ascii_case(extension))
+            });
+        let method = if stored {
+            zip::CompressionMethod::Stored
+        } else {
+            self.compression.method()
+        };
+        zip::write::SimpleFileOptions::default().compression_method(method)
+    }
+}
+
+/// Range of clip lengths that are archived, either end is open when unset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DurationFilter {
+    pub min_seconds: Option<f64>,
+    pub max_seconds: Option<f64>,
+}
+
+impl DurationFilter {
+    fn contains(self, seconds: f64) -> bool {
+        self.min_seconds.is_none_or(|min| seconds >= min)
+            && self.max_seconds.is_none_or(|max| seconds <= max)
+    }
+}
+
+#[derive(Clone)]
+pub struct ArchiveOptions {
+    pub include_text: bool,
+    /// Add the first frame of each clip as a `.jpg` next to it, needs ffmpeg
+    pub thumbnails: bool,
+    /// Clips larger than this are skipped, on top of the total size limit
+    pub max_file_size: Option<u64>,
+    /// Submissions are ordered oldest first instead of newest first
+    pub oldest_first: bool,
+    /// Prefix file names with the clip's position in the archive
+    pub numbered: bool,
+    /// Name clips after their sanitized original file name instead of the move and submitter,
+    /// with the attachment id added when two clips have the same name
+    pub preserve_names: bool,
+    pub compression: Compression,
+    /// Lowercase extensions of already compressed files, stored as they are instead of compressed
+    pub stored_extensions: Vec<String>,
+    /// Re-encode clips to make them smaller, needs ffmpeg
+    pub transcode: Option<TranscodeSettings>,
+    /// Only clips this long are archived, needs ffprobe
+    pub duration: Option<DurationFilter>,
+    /// Attachments downloaded at the same time
+    pub download_concurrency: NonZeroUsize,
+    /// Pause between starting downloads
+    pub download_delay: Duration,
+}
+
+pub struct ArchiveResult {
+    pub archive: PathBuf,
+    /// Number of clips written to the archive
+    pub clips: usize,
+    /// Attachments written to the archive
+    pub attachment_ids: Vec<AttachmentId>,
+    pub notes: Vec<String>,
+}
+
+/// Signed attachment url that's no longer valid
+#[derive(Debug, thiserror::Error)]
+#[error("Attachment url has expired")]
+pub struct ExpiredUrl;
+
+/// Where attachments are downloaded from, several at a time
+pub trait AttachmentSource: Sync {
+    /// Fails with [`ExpiredUrl`] if the attachment's url has expired
+    fn fetch(&self, attachment: &Attachment) -> anyhow::Result<Box<dyn Read + '_>>;
+
+    /// The submission's attachment with a freshly signed url, `None` if it can't be refreshed
+    fn refresh(&self, _submission: &Submission) -> anyhow::Result<Option<Attachment>> {
+        Ok(None)
+    }
+}
+
+/// Downloads attachments from the Discord CDN
+pub struct HttpSource {
+    client: reqwest::blocking::Client,
+    refresh: Option<Refresh>,
+}
+
+/// Re-fetches messages to get new attachment urls
+struct Refresh {
+    http: Arc<serenity::Http>,
+    runtime: tokio::runtime::Handle,
+}
+
+impl HttpSource {
+    pub fn new(timeout: Duration, user_agent: &str) -> anyhow::Result<Self> {
+        // attachments are already compressed video, decoding them again only wastes CPU
+        let client = reqwest::blocking::Client::builder()
+            .user_agent(user_agent)
+            .timeout(timeout)
+            .no_gzip()
+            .no_brotli()
+            .build()
+            .context("Failed to create HTTP client")?;
+        Ok(Self {
+            client,
+            refresh: None,
+        })
+    }
+
+    /// Refreshes expired attachment urls by re-fetching their messages,
+    /// `runtime` runs the Discord requests from the blocking archive thread
+    pub fn with_refresh(
+        mut self,
+        http: Arc<serenity::Http>,
+        runtime: tokio::runtime::Handle,
+    ) -> Self {
+        self.refresh = Some(Refresh { http, runtime });
+        self
+    }
+}
+
+impl AttachmentSource for HttpSource {
+    fn fetch(&self, attachment: &Attachment) -> anyhow::Result<Box<dyn Read + '_>> {
+        let response = self
+            .client
+            .get(&attachment.url)
+            .send()
+            .context("Failed to get attachment")?;
+
+        // the CDN answers expired signed urls with 403 or 404
+        if matches!(
+            response.status(),
+            reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::NOT_FOUND
+        ) {
+            return Err(ExpiredUrl.into());
+        }
+        Ok(Box::new(response))
+    }
+
+    fn refresh(&self, submission: &Submission) -> anyhow::Result<Option<Attachment>> {
+        let Some(refresh) = &self.refresh else {
+            return Ok(None);
+        };
+
+        let message = refresh
+            .runtime
+            .block_on(
+                submission
+                    .channel_id
+                    .message(&refresh.http, submission.message_id),
+            )
+            .context(format!(
+                "Failed to re-fetch message {}",
+                submission.message_id
+            ))?;
+        Ok(message
+            .attachments
+            .into_iter()
+            .find(|attachment| attachment.id == submission.attachment.id))
+    }
+}
+
+/// Downloads the submission's attachment, refreshing its url once if it expired.
+/// `None` if the url couldn't be refreshed
+fn fetch_fresh<'a>(
+    source: &'a dyn AttachmentSource,
+    submission: &Submission,
+) -> anyhow::Result<Option<Box<dyn Read + 'a>>> {
+    match source.fetch(&submission.attachment) {
+        Err(e) if e.is::<ExpiredUrl>() => {}
+        result => return result.map(Some),
+    }
+
+    tracing::info!(
+        "Url of attachment {} expired, refreshing it",
+        submission.attachment.id
+    );
+    // a deleted message 404s, that clip is skipped instead of failing the whole archive
+    let attachment = match source.refresh(submission) {
+        Ok(Some(attachment)) => attachment,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            tracing::warn!(
+                "Failed to refresh the url of attachment {}: {:#}",
+                submission.attachment.id,
+                e
+            );
+            return Ok(None);
+        }
+    };
+    match source.fetch(&attachment) {
+        Err(e) if e.is::<ExpiredUrl>() => Ok(None),
+        result => result.map(Some),
+    }
+}
+
+/// Why an archive task on a blocking thread didn't finish
+#[derive(Debug, thiserror::Error)]
+pub enum TaskError {
+    #[error("{0:#}")]
+    Failed(anyhow::Error),
+    #[error("archive task panicked: {0}")]
+    Panicked(String),
+}
+
+/// Runs `task` on a blocking thread with the path of `dir`, giving `dir` back when it succeeds.
+/// When it fails or panics `dir` is removed before returning, a panic is logged with its message
+pub async fn run_in_dir<T, F>(dir: TempDir, task: F) -> Result<(TempDir, T), TaskError>
+where
+    T: Send + 'static,
+    F: FnOnce(&Path) -> anyhow::Result<T> + Send + 'static,
+{
+    let path = dir.path().to_owned();
+    let result = match tokio::task::spawn_blocking(move || task(&path)).await {
+        Ok(Ok(value)) => return Ok((dir, value)),
+        Ok(Err(e)) => TaskError::Failed(e),
+        Err(e) if e.is_panic() => {
+            let panic = e.into_panic();
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "no message".to_owned());
+            tracing::error!("Archive task panicked: {}", message);
+            TaskError::Panicked(message)
+        }
+        Err(e) => TaskError::Failed(anyhow::Error::new(e).context("Archive task was cancelled")),
+    };
+
+    let path = dir.path().to_owned();
+    if let Err(e) = dir.close() {
+        tracing::warn!(
+            "Failed to remove temporary directory {}: {}",
+            path.display(),
+            e
+        );
+    }
+    Err(result)
+}
+
+/// Downloads the submissions into a zip archive at `zip_file_path`, blocks until done.
+/// `thread_info` is written first as `thread-info.txt`
+pub fn create_archive(
+    source: &dyn AttachmentSource,
+    zip_file_path: PathBuf,
+    move_name: &str,
+    submissions: Vec<Submission>,
+    mut notes: Vec<String>,
+    thread_info: Option<&str>,
+    options: ArchiveOptions,
+) -> anyhow::Result<ArchiveResult> {
+    let zip_file = std::fs::File::create(&zip_file_path).context("Failed to create archive")?;
+    let mut zip = zip::ZipWriter::new(zip_file);
+
+    let mut total_size = 0;
+    let mut file_names = HashSet::new();
+    if let Some(thread_info) = thread_info {
+        // reserved so no clip is named like it
+        file_names.insert("thread-info.txt".to_owned());
+        zip.start_file("thread-info.txt", options.file_options("thread-info.txt"))
+            .context("Failed to start writing thread info")?;
+        zip.write_all(thread_info.as_bytes())
+            .context("Failed to write thread info")?;
+    }
+    let mut thumbnails = options.thumbnails;
+    let mut transcode = options.transcode;
+    if transcode.is_some() && !transcode::ffmpeg_available() {
+        tracing::warn!("ffmpeg is not installed, skipping transcoding");
+        notes.push("Clips were archived as posted because ffmpeg isn't installed".to_owned());
+        transcode = None;
+    }
+    let mut too_large = Vec::new();
+    let mut duration = options.duration;
+    let mut wrong_length = Vec::new();
+    let mut archived = 0;
+    let mut attachment_ids = Vec::new();
+    let index_width = submissions.len().to_string().len();
+    let max_file_size = options
+        .max_file_size
+        .map_or(MAX_TOTAL_SIZE_BYTES, |max| max.min(MAX_TOTAL_SIZE_BYTES));
+
+    let concurrency = options.download_concurrency.get();
+    let mut submissions = submissions.into_iter();
+    let mut size_limit_reached = false;
+    let mut first_batch = true;
+    while !size_limit_reached {
+        // size limits are checked before downloading, so no download is wasted on them
+        let mut batch = Vec::with_capacity(concurrency);
+        while batch.len() < concurrency {
+            let Some(submission) = submissions.next() else {
+                break;
+            };
+            if Path::new(&submission.attachment.filename)
+                .extension()
+                .is_none()
+            {
+                continue;
+            }
+
+            // a single clip that can never fit is skipped instead of ending the archive early
+            if submission.attachment.size as u64 > max_file_size {
+                too_large.push(format!(
+                    "{} ({})",
+                    submission.attachment.filename,
+                    format_size(submission.attachment.size as u64)
+                ));
+                continue;
+            }
+
+            if total_size + submission.attachment.size as u64 > MAX_TOTAL_SIZE_BYTES {
+                notes.push(format!(
+                    "Size limit 512MB reached. Messages from {} and {} were not downloaded",
+                    submission.attachment.id.created_at(),
+                    if options.oldest_first {
+                        "later"
+                    } else {
+                        "earlier"
+                    }
+                ));
+                size_limit_reached = true;
+                break;
+            }
+            total_size += submission.attachment.size as u64;
+            batch.push(submission);
+        }
+        if batch.is_empty() {
+            break;
+        }
+
+        if !first_batch {
+            std::thread::sleep(options.download_delay);
+        }
+        first_batch = false;
+        let downloads = download_batch(source, &batch, options.download_delay);
+
+        // clips are checked first, so only the ones that are archived get transcoded
+        let mut checked = Vec::with_capacity(batch.len());
+        for (submission, download) in batch.into_iter().zip(downloads) {
+            let Some((clip, downloaded)) = download? else {
+                total_size -= submission.attachment.size as u64;
+                notes.push(format!(
+                    "{} from {} was skipped because its link expired and couldn't be refreshed",
+                    submission.attachment.filename, submission.username
+                ));
+                continue;
+            };
+
+            // truncated downloads would otherwise end up as corrupt clips in the archive
+            if downloaded != submission.attachment.size as u64 {
+                total_size -= submission.attachment.size as u64;
+                notes.push(incomplete(&submission, downloaded));
+                continue;
+            }
+
+            if let Some(filter) = duration {
+                match probe::duration(clip.path()) {
+                    Ok(Some(seconds)) if !filter.contains(seconds) => {
+                        total_size -= submission.attachment.size as u64;
+                        wrong_length.push(format!(
+                            "{} ({:.0}s)",
+                            submission.attachment.filename, seconds
+                        ));
+                        continue;
+                    }
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        tracing::warn!("ffprobe is not installed, skipping the duration filter");
+                        notes.push(
+                            "Clips of any length were archived because ffprobe isn't installed"
+                                .to_owned(),
+                        );
+                        duration = None;
+                    }
+                    Err(e) => tracing::warn!(
+                        "Failed to get the duration of attachment {}, archiving it: {:#}",
+                        submission.attachment.id,
+                        e
+                    ),
+                }
+            }
+
+            checked.push((submission, clip));
+        }
+
+        let transcoded = match transcode {
+            Some(settings) => transcode_batch(&checked, settings),
+            None => checked.iter().map(|_| None).collect(),
+        };
+
+        for ((submission, mut clip), transcoded) in checked.into_iter().zip(transcoded) {
+            let mut file_extension = Path::new(&submission.attachment.filename)
+                .extension()
+                .and_then(std::ffi::OsStr::to_str)
+                .unwrap_or_default();
+            match transcoded {
+                Some(Ok(transcoded)) => {
+                    clip = transcoded;
+                    file_extension = "mp4";
+                }
+                Some(Err(e)) => {
+                    tracing::warn!(
+                        "Failed to transcode attachment {}: {:#}",
+                        submission.attachment.id,
+                        e
+                    );
+                    notes.push(format!(
+                        "{} from {} couldn't be transcoded and was archived as posted",
+                        submission.attachment.filename, submission.username
+                    ));
+                }
+                None => {}
+            }
+
+            // the clip's path in the archive, with its position and folder
+            let in_archive = |mut file_name: String| {
+                if options.numbered {
+                    file_name = format!(
+                        "{:0width$}-{}",
+                        archived + 1,
+                        file_name,
+                        width = index_width
+                    );
+                }
+                match &submission.folder {
+                    Some(folder) => format!("{}/{}", folder, file_name),
+                    None => file_name,
+                }
+            };
+            let new_file_name = if options.preserve_names {
+                let original_stem = Path::new(&submission.attachment.filename)
+                    .file_stem()
+                    .and_then(std::ffi::OsStr::to_str)
+                    .unwrap_or_default();
+                let stem = sanitize_file_stem(original_stem);
+                let preserved = in_archive(format!("{}.{}", stem, file_extension));
+                if stem.is_empty() || file_names.contains(&preserved) {
+                    in_archive(
+                        format!("{}-{}.{}", stem, submission.attachment.id, file_extension)
+                            .trim_start_matches('-')
+                            .to_owned(),
+                    )
+                } else {
+                    preserved
+                }
+            } else {
+                in_archive(format!(
+                    "{}-{}-{}.{}",
+                    submission.move_name.as_deref().unwrap_or(move_name),
+                    &submission.username,
+                    submission.attachment.id,
+                    file_extension
+                ))
+            };
+            let new_file_name = unique_file_name(&mut file_names, new_file_name);
+
+            zip.start_file(&new_file_name, options.file_options(&new_file_name))
+                .context(format!(
+                    "Failed to start writing attachment {}",
+                    submission.attachment.id,
+                ))?;
+            std::io::copy(&mut clip.as_file(), &mut zip).context(format!(
+                "Failed to write attachment {}",
+                submission.attachment.id
+            ))?;
+
+            archived += 1;
+            attachment_ids.push(submission.attachment.id);
+
+            if thumbnails {
+                match thumbnail::first_frame(clip.path()) {
+                    Ok(Some(frame)) => {
+                        let thumbnail_file_name = unique_file_name(
+                            &mut file_names,
+                            Path::new(&new_file_name)
+                                .with_extension("jpg")
+                                .to_string_lossy()
+                                .into_owned(),
+                        );
+                        zip.start_file(
+                            &thumbnail_file_name,
+                            options.file_options(&thumbnail_file_name),
+                        )
+                        .context(format!(
+                            "Failed to start writing thumbnail of attachment {}",
+                            submission.attachment.id,
+                        ))?;
+                        zip.write_all(&frame).context(format!(
+                            "Failed to write thumbnail of attachment {}",
+                            submission.attachment.id
+                        ))?;
+                    }
+                    Ok(None) => {
+                        tracing::warn!("ffmpeg is not installed, skipping thumbnails");
+                        notes.push(
+                            "Thumbnails were skipped because ffmpeg isn't installed".to_owned(),
+                        );
+                        thumbnails = false;
+                    }
+                    Err(e) => tracing::warn!(
+                        "Failed to create thumbnail of attachment {}: {:#}",
+                        submission.attachment.id,
+                        e
+                    ),
+                }
+            }
+
+            if options.include_text && !submission.text.trim().is_empty() {
+                let text_file_name = unique_file_name(
+                    &mut file_names,
+                    Path::new(&new_file_name)
+                        .with_extension("txt")
+                        .to_string_lossy()
+                        .into_owned(),
+                );
+                zip.start_file(&text_file_name, options.file_options(&text_file_name))
+                    .context(format!(
+                        "Failed to start writing message text of attachment {}",
+                        submission.attachment.id,
+                    ))?;
+                zip.write_all(submission.text.as_bytes()).context(format!(
+                    "Failed to write message text of attachment {}",
+                    submission.attachment.id
+                ))?;
+            }
+        }
+    }
+
+    zip.finish()
+        .context("Failed to finish writing to archive")?;
+
+    notes.extend(skipped_note("too large", &too_large));
+    notes.extend(skipped_note("length out of range", &wrong_length));
+
+    Ok(ArchiveResult {
+        archive: zip_file_path,
+        clips: archived,
+        attachment_ids,
+        notes,
+    })
+}
+
+/// Note listing the first skipped clips and counting the rest, `None` if none were skipped
+fn skipped_note(reason: &str, clips: &[String]) -> Option<String> {
+    if clips.is_empty() {
+        return None;
+    }
+
+    let mut skipped = clips
+        .iter()
+        .take(MAX_LISTED_SKIPPED)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if clips.len() > MAX_LISTED_SKIPPED {
+        skipped.push_str(&format!(" and {} more", clips.len() - MAX_LISTED_SKIPPED));
+    }
+    Some(format!("Skipped ({}): {}", reason, skipped))
+}
+
+/// Transcodes the clips, `settings.parallel` of them at a time
+fn transcode_batch(
+    clips: &[(Submission, NamedTempFile)],
+    settings: TranscodeSettings,
+) -> Vec<Option<anyhow::Result<NamedTempFile>>> {
+    let next = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<anyhow::Result<NamedTempFile>>>> =
+        clips.iter().map(|_| Mutex::new(None)).collect();
+    std::thread::scope(|scope| {
+        for _ in 0..settings.parallel.get().min(clips.len()) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some((_, clip)) = clips.get(index) else {
+                        break;
+                    };
+                    let transcoded = transcode::transcode(clip.path(), settings);
+                    *results[index].lock().unwrap() = Some(transcoded);
+                }
+            });
+        }
+    });
+    results
+        .into_iter()
+        .map(|result| result.into_inner().unwrap())
+        .collect()
+}
+
+/// Downloads the attachments of `batch` in parallel, starting the requests `delay` apart
+fn download_batch(
+    source: &dyn AttachmentSource,
+    batch: &[Submission],
+    delay: Duration,
+) -> Vec<anyhow::Result<Option<(NamedTempFile, u64)>>> {
+    std::thread::scope(|scope| {
+        let downloads: Vec<_> = batch
+            .iter()
+            .enumerate()
+            .map(|(index, submission)| {
+                scope.spawn(move || {
+                    std::thread::sleep(delay * index as u32);
+                    download(source, submission)
+                })
+            })
+            .collect();
+        downloads
+            .into_iter()
+            .map(|download| {
+                download
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    })
+}
+
+/// Downloads the submission's attachment to a clip file and returns it with the bytes received,
+/// `None` if its url expired and couldn't be refreshed
+fn download(
+    source: &dyn AttachmentSource,
+    submission: &Submission,
+) -> anyhow::Result<Option<(NamedTempFile, u64)>> {
+    let Some(mut response) = fetch_fresh(source, submission)? else {
+        return Ok(None);
+    };
+
+    // ffmpeg needs the whole clip on disk to read it, and it's written to the archive in order
+    let mut file = NamedTempFile::new().context("Failed to create clip file")?;
+    let downloaded = std::io::copy(&mut response, &mut file).context(format!(
+        "Failed to download attachment {}",
+        submission.attachment.id
+    ))?;
+    file.rewind().context("Failed to rewind clip file")?;
+    Ok(Some((file, downloaded)))
+}
+
+/// Logs the incomplete download of the submission's attachment and returns the note about it
+fn incomplete(submission: &Submission, downloaded: u64) -> String {
+    tracing::warn!(
+        "Attachment {} is incomplete, got {} of {} bytes",
+        submission.attachment.id,
+        downloaded,
+        submission.attachment.size
+    );
+    format!(
+        "{} from {} was skipped because the download was incomplete",
+        submission.attachment.filename, submission.username
+    )
+}
+
+/// Replaces characters other than letters, digits, `-`, `_` and `.` with `_`,
+/// without leading or trailing dots and underscores
+pub fn sanitize_file_stem(stem: &str) -> String {
+    let stem: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    stem.trim_matches(|c| c == '.' || c == '_').to_owned()
+}
+
+/// Size in whole megabytes, e.g. `600MB`
+pub fn format_size(bytes: u64) -> String {
+    format!("{}MB", bytes.div_ceil(1024 * 1024))
+}
+
+/// Number of clips with the noun, e.g. `1 clip` or `3 clips`
+pub fn clip_count(clips: usize) -> String {
+    format!("{} clip{}", clips, if clips == 1 { "" } else { "s" })
+}
+
+/// Appends an incrementing suffix to `file_name` if it's already in `used`,
+/// keeping the folder it's in
+fn unique_file_name(used: &mut HashSet<String>, file_name: String) -> String {
+    if used.insert(file_name.clone()) {
+        return file_name;
+    }
+
+    let path = Path::new(&file_name);
+    let stem = path
+        .file_stem()
+        .and_then(std::ffi::OsStr::to_str)
+        .unwrap_or(&file_name);
+    let extension = path.extension().and_then(std::ffi::OsStr::to_str);
+
+    let mut suffix = 1;
+    loop {
+        let candidate = match extension {
+            Some(ext) => format!("{}-{}.{}", stem, suffix, ext),
+            None => format!("{}-{}", stem, suffix),
+        };
+        let candidate = path
+            .with_file_name(candidate)
+            .to_string_lossy()
+            .into_owned();
+        if used.insert(candidate.clone()) {
+            return candidate;
+        }
+        suffix += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_file_name_disambiguates_collisions() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_file_name(&mut used, "a-b.mp4".to_owned()), "a-b.mp4");
+        assert_eq!(
+            unique_file_name(&mut used, "a-b.mp4".to_owned()),
+            "a-b-1.mp4"
+        );
+        assert_eq!(
+            unique_file_name(&mut used, "a-b.mp4".to_owned()),
+            "a-b-2.mp4"
+        );
+        assert_eq!(unique_file_name(&mut used, "a-b.mov".to_owned()), "a-b.mov");
+        assert_eq!(
+            unique_file_name(&mut used, "a-b-1.mp4".to_owned()),
+            "a-b-1-1.mp4"
+        );
+        assert_eq!(
+            unique_file_name(&mut used, "t/a-b.mp4".to_owned()),
+            "t/a-b.mp4"
+        );
+        assert_eq!(
+            unique_file_name(&mut used, "t/a-b.mp4".to_owned()),
+            "t/a-b-1.mp4"
+        );
+    }
+}
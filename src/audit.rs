@@ -0,0 +1,33 @@
+use poise::serenity_prelude::{self as serenity, ChannelId, Timestamp, User};
+use std::sync::Arc;
+
+/// Longest invocation posted to the audit channel, long arguments are cut off
+const MAX_AUDIT_INVOCATION_LENGTH: usize = 200;
+
+/// Posts who ran which command to the audit channel in the background, failures are only logged
+pub fn record(http: Arc<serenity::Http>, channel_id: ChannelId, user: &User, invocation: String) {
+    let invocation = if invocation.chars().count() > MAX_AUDIT_INVOCATION_LENGTH {
+        let mut truncated: String = invocation
+            .chars()
+            .take(MAX_AUDIT_INVOCATION_LENGTH)
+            .collect();
+        truncated.push('…');
+        truncated
+    } else {
+        invocation
+    };
+    // names instead of mentions, mentions would ping everyone in the log
+    let line = format!(
+        "<t:{}:f> {} ({}) ran `{}`",
+        Timestamp::now().unix_timestamp(),
+        user.name,
+        user.id,
+        invocation.replace('`', "'")
+    );
+
+    tokio::spawn(async move {
+        if let Err(e) = channel_id.say(&http, line).await {
+            tracing::warn!("Failed to post to the audit channel: {}", e);
+        }
+    });
+}
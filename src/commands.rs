@@ -0,0 +1,49 @@
+mod audit_threads;
+mod browse;
+mod config;
+mod delete_upload;
+mod estimate;
+mod export_moves;
+mod grab;
+mod history;
+mod metrics;
+mod missing;
+mod perms;
+mod pull;
+mod pull_many;
+mod reload_config;
+mod report;
+mod reupload;
+mod search;
+mod search_debug;
+mod selftest;
+mod status;
+mod validate_moves;
+
+use crate::data::{Data, Error};
+
+pub fn all() -> Vec<poise::Command<Data, Error>> {
+    vec![
+        pull::pull(),
+        pull_many::pull_many(),
+        grab::grab(),
+        estimate::estimate(),
+        search::search(),
+        search_debug::search_debug(),
+        browse::browse(),
+        status::status(),
+        report::report(),
+        delete_upload::delete_upload(),
+        metrics::metrics(),
+        reupload::reupload(),
+        validate_moves::validate_moves(),
+        history::history(),
+        export_moves::export_moves(),
+        missing::missing(),
+        audit_threads::audit_threads(),
+        reload_config::reload_config(),
+        config::config(),
+        selftest::selftest(),
+        perms::perms(),
+    ]
+}
@@ -0,0 +1,129 @@
+use crate::commands::pull;
+use crate::data::{Context, Error};
+use crate::moves::MOVE_LIST_NOT_CONFIGURED;
+use crate::reply;
+use anyhow::Context as AnyhowContext;
+use poise::serenity_prelude::{self as serenity, ChannelId, ChannelType};
+use tracing::instrument;
+
+/// Problems listed per page of the reply
+const PAGE_SIZE: usize = 50;
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    required_permissions = "ADMINISTRATOR"
+)]
+#[instrument(name = "audit_threads", skip_all, fields(id = ctx.id(), username = ctx.author().name, page = page))]
+pub async fn audit_threads(
+    ctx: Context<'_>,
+    #[description = "Page of the report, 50 moves each"]
+    #[min = 1]
+    page: Option<usize>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral()
+        .await
+        .context("Failed to defer response")?;
+
+    let move_list = ctx.data().move_list.load_full();
+    if move_list.is_empty() {
+        reply::send_text(ctx, MOVE_LIST_NOT_CONFIGURED).await?;
+        return Ok(());
+    }
+
+    let mut problems = Vec::new();
+    for move_name in move_list.iter() {
+        let problem = match ctx.data().move_thread(move_name) {
+            Some(thread_id) => thread_problem(ctx, thread_id).await,
+            None => Some("no thread".to_owned()),
+        };
+        if let Some(problem) = problem {
+            problems.push(format!("{}: {}", move_name, problem));
+        }
+    }
+
+    if problems.is_empty() {
+        reply::send_text(
+            ctx,
+            format!(
+                "Every one of the {} moves has a thread I can access",
+                move_list.len()
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let pages = problems.len().div_ceil(PAGE_SIZE);
+    let page = page.unwrap_or(1).clamp(1, pages);
+    let mut reply = format!(
+        "{} of {} moves have no thread I can access, page {} of {}:\n{}",
+        problems.len(),
+        move_list.len(),
+        page,
+        pages,
+        problems
+            .iter()
+            .skip((page - 1) * PAGE_SIZE)
+            .take(PAGE_SIZE)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    if pages > 1 {
+        reply.push_str("\nUse `/audit_threads` with `page` to see the other pages");
+    }
+
+    reply::send_text_or_file(ctx, reply, "thread-audit.txt").await?;
+
+    Ok(())
+}
+
+/// Why the thread `thread_id` of a move can't be pulled from, `None` if it can.
+/// Failed requests are reported as the problem, so one thread doesn't end the audit
+async fn thread_problem(ctx: Context<'_>, thread_id: ChannelId) -> Option<String> {
+    let channel = match thread_id.to_channel(ctx).await {
+        Ok(channel) => channel,
+        Err(serenity::Error::Http(e))
+            if e.status_code() == Some(serenity::StatusCode::NOT_FOUND) =>
+        {
+            return Some(format!("thread {} was deleted", thread_id));
+        }
+        Err(serenity::Error::Http(e))
+            if e.status_code() == Some(serenity::StatusCode::FORBIDDEN) =>
+        {
+            return Some(format!("thread <#{}> isn't accessible to me", thread_id));
+        }
+        Err(e) => {
+            tracing::warn!("Failed to get thread {}: {}", thread_id, e);
+            return Some(format!(
+                "thread <#{}> couldn't be checked: {}",
+                thread_id, e
+            ));
+        }
+    };
+
+    let Some(channel) = channel.guild() else {
+        return Some(format!("{} isn't a server thread", thread_id));
+    };
+    if Some(channel.guild_id) != ctx.guild_id() {
+        return Some(format!("thread {} is in another server", thread_id));
+    }
+    match channel.kind {
+        ChannelType::NewsThread | ChannelType::PublicThread => None,
+        // private threads look empty instead of failing to the bots that aren't in them
+        ChannelType::PrivateThread => match pull::is_thread_member(ctx, &channel).await {
+            Ok(true) => None,
+            Ok(false) => Some(format!("private thread <#{}> hasn't added me", thread_id)),
+            Err(e) => {
+                tracing::warn!("Failed to check membership of thread {}: {}", thread_id, e);
+                Some(format!(
+                    "thread <#{}> couldn't be checked: {}",
+                    thread_id, e
+                ))
+            }
+        },
+        _ => Some(format!("<#{}> isn't a thread", thread_id)),
+    }
+}
@@ -0,0 +1,76 @@
+use crate::data::{Context, Error};
+use crate::moves::{MOVE_LIST_NOT_CONFIGURED, move_title};
+use crate::reply;
+use std::collections::BTreeMap;
+use tracing::instrument;
+
+/// Index letter of a move, moves whose title doesn't start with a letter go under '#'
+fn browse_letter(move_name: &str) -> char {
+    match move_title(move_name).chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => c.to_ascii_uppercase(),
+        _ => '#',
+    }
+}
+
+#[poise::command(slash_command)]
+#[instrument(name = "browse", skip_all, fields(id = ctx.id(), username = ctx.author().name, letter = letter))]
+pub async fn browse(
+    ctx: Context<'_>,
+    #[description = "First letter of the move title, or # for non-letters"] letter: Option<String>,
+) -> Result<(), Error> {
+    let move_list = ctx.data().move_list.load_full();
+    if move_list.is_empty() {
+        reply::send_text(ctx, MOVE_LIST_NOT_CONFIGURED).await?;
+        return Ok(());
+    }
+
+    let mut index: BTreeMap<char, Vec<&str>> = BTreeMap::new();
+    for move_name in move_list.iter() {
+        index
+            .entry(browse_letter(move_name))
+            .or_default()
+            .push(move_name);
+    }
+
+    let letter = letter.and_then(|l| {
+        let mut chars = l.trim().chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c.to_ascii_uppercase()),
+            _ => None,
+        }
+    });
+
+    let reply = match letter {
+        Some(letter) => match index.get(&letter) {
+            Some(moves) => format!("Moves starting with {}:\n{}", letter, moves.join("\n")),
+            None => format!("No move starts with {}", letter),
+        },
+        None => format!(
+            "Use `/browse <letter>` to list the moves starting with that letter:\n{}",
+            index
+                .iter()
+                .map(|(letter, moves)| format!("{} ({})", letter, moves.len()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+
+    reply::send_text_or_file(ctx, reply, "moves.txt").await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn browse_letter_uses_move_title() {
+        assert_eq!(
+            browse_letter("02-false_shuffles-0107-conleys_three_riffle_variation"),
+            'C'
+        );
+        assert_eq!(browse_letter("05-false_deals-0409-2nd_deal"), '#');
+        assert_eq!(browse_letter("not-a-move"), 'N');
+    }
+}
@@ -0,0 +1,35 @@
+use crate::config::RESTART_REQUIRED;
+use crate::data::{Context, Error};
+use crate::reply;
+use tracing::instrument;
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    required_permissions = "ADMINISTRATOR"
+)]
+#[instrument(name = "config", skip_all, fields(id = ctx.id(), username = ctx.author().name))]
+pub async fn config(ctx: Context<'_>) -> Result<(), Error> {
+    let settings = ctx.data().config.load().redacted();
+
+    let lines: Vec<String> = settings
+        .iter()
+        .map(|(name, value)| {
+            let marker = if RESTART_REQUIRED.contains(name) {
+                "*"
+            } else {
+                ""
+            };
+            format!("{}{} = {}", name, marker, value)
+        })
+        .collect();
+    let reply = format!(
+        "Settings in effect, webhook urls are hidden. * takes a restart to change\n```\n{}\n```",
+        lines.join("\n")
+    );
+
+    reply::send_text_or_file(ctx, reply, "config.txt").await?;
+
+    Ok(())
+}
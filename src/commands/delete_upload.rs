@@ -0,0 +1,63 @@
+use crate::data::{Context, Error};
+use crate::error::BotError;
+use crate::reply;
+use tracing::instrument;
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    required_permissions = "ADMINISTRATOR"
+)]
+#[instrument(name = "delete_upload", skip_all, fields(id = ctx.id(), username = ctx.author().name, link = link))]
+pub async fn delete_upload(
+    ctx: Context<'_>,
+    #[description = "Download link of the archive"] link: String,
+) -> Result<(), Error> {
+    let link = link.trim();
+    // the record is kept until the host deleted the upload, so a failed delete can be retried
+    let record = ctx
+        .data()
+        .history
+        .lock()
+        .unwrap()
+        .find_by_link(link, ctx.guild_id())
+        .map(|record| {
+            (
+                record.url.clone(),
+                record.link.clone(),
+                record.token.clone(),
+                record.moves.join(", "),
+            )
+        });
+
+    let reply = match record {
+        Some((url, record_link, Some(token), moves)) => {
+            ctx.data()
+                .uploader
+                .delete(&url, &token)
+                .await
+                .map_err(BotError::Upload)?;
+            ctx.data()
+                .history
+                .lock()
+                .unwrap()
+                .remove_by_link(link, ctx.guild_id());
+            ctx.data()
+                .recent_uploads
+                .lock()
+                .unwrap()
+                .remove_link(&record_link);
+            format!("Deleted the archive of {}", moves)
+        }
+        Some((_, _, None, moves)) => format!(
+            "No management token was returned for the archive of {}, it can't be deleted",
+            moves
+        ),
+        None => "No upload with that link was found in this server".to_owned(),
+    };
+
+    reply::send_text(ctx, reply).await?;
+
+    Ok(())
+}
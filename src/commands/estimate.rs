@@ -0,0 +1,83 @@
+use crate::archive::{clip_count, format_size};
+use crate::commands::pull;
+use crate::config::MAX_TOTAL_SIZE_BYTES;
+use crate::data::{Context, Error};
+use crate::reply;
+use anyhow::Context as AnyhowContext;
+use std::time::Duration;
+use tracing::instrument;
+
+#[poise::command(slash_command, guild_only)]
+#[instrument(name = "estimate", skip_all, fields(id = ctx.id(), username = ctx.author().name))]
+pub async fn estimate(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral()
+        .await
+        .context("Failed to defer response")?;
+
+    let Some(thread) = pull::current_thread(ctx).await? else {
+        return Ok(());
+    };
+
+    let config = ctx.data().config.load_full();
+    let (submissions, mut notes) = pull::thread_clips(ctx, &config, &thread).await?;
+    if submissions.is_empty() {
+        reply::send_text(ctx, "No videos found in this thread").await?;
+        return Ok(());
+    }
+
+    // like a pull, clips past the size limit are left out of the archive
+    let max_file_size = config
+        .max_file_size_bytes
+        .map_or(MAX_TOTAL_SIZE_BYTES, |max| max.min(MAX_TOTAL_SIZE_BYTES));
+    let mut clips = 0;
+    let mut size = 0;
+    for submission in &submissions {
+        let clip_size = submission.attachment.size as u64;
+        if clip_size > max_file_size {
+            continue;
+        }
+        if size + clip_size > MAX_TOTAL_SIZE_BYTES {
+            notes.push("Size limit 512MB reached, later clips are left out".to_owned());
+            break;
+        }
+        clips += 1;
+        size += clip_size;
+    }
+
+    let megabytes = size as f64 / (1024.0 * 1024.0);
+    let seconds = megabytes / config.estimate_download_mb_per_second
+        + megabytes / config.estimate_upload_mb_per_second;
+
+    let mut reply = format!(
+        "{}, {} in total. A pull should take {}",
+        clip_count(clips),
+        format_size(size),
+        format_duration(Duration::from_secs_f64(seconds))
+    );
+    for note in notes {
+        reply.push('\n');
+        reply.push_str(&note);
+    }
+    reply::send_text(ctx, reply).await?;
+
+    Ok(())
+}
+
+/// Rough duration, e.g. `about 3 minutes`
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs().max(1);
+    if seconds < 60 {
+        format!(
+            "about {} second{}",
+            seconds,
+            if seconds == 1 { "" } else { "s" }
+        )
+    } else {
+        let minutes = seconds.div_ceil(60);
+        format!(
+            "about {} minute{}",
+            minutes,
+            if minutes == 1 { "" } else { "s" }
+        )
+    }
+}
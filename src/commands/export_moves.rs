@@ -0,0 +1,69 @@
+use crate::data::{Context, Error};
+use crate::error::BotError;
+use crate::moves;
+use crate::reply;
+use crate::upload;
+use anyhow::Context as AnyhowContext;
+use tracing::instrument;
+
+/// Largest file Discord accepts as an attachment without boosts
+const MAX_ATTACHMENT_BYTES: usize = 8 * 1024 * 1024;
+const EXPORT_FILE_NAME: &str = "move-list.txt";
+
+#[poise::command(slash_command)]
+#[instrument(name = "export_moves", skip_all, fields(id = ctx.id(), username = ctx.author().name))]
+pub async fn export_moves(ctx: Context<'_>) -> Result<(), Error> {
+    let move_list = ctx.data().move_list.load_full();
+    if move_list.is_empty() {
+        reply::send_text(ctx, moves::MOVE_LIST_NOT_CONFIGURED).await?;
+        return Ok(());
+    }
+
+    let export: String = move_list
+        .iter()
+        .map(|move_name| match moves::move_category(move_name) {
+            Some(category) => format!("{}\t{}\n", move_name, category),
+            None => format!("{}\n", move_name),
+        })
+        .collect();
+    let summary = format!("{} moves", move_list.len());
+
+    if export.len() <= MAX_ATTACHMENT_BYTES {
+        return reply::send_file(ctx, summary, export.into_bytes(), EXPORT_FILE_NAME).await;
+    }
+
+    ctx.defer_ephemeral()
+        .await
+        .context("Failed to defer response")?;
+
+    let dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let path = dir.path().join(EXPORT_FILE_NAME);
+    tokio::fs::write(&path, export)
+        .await
+        .context("Failed to write move list")?;
+
+    let expires_hours = ctx.data().config.load().upload_expires_hours;
+    let response = ctx
+        .data()
+        .uploader
+        .upload(path, expires_hours)
+        .await
+        .map_err(BotError::Upload)?;
+    let reply = match upload::parse_download_url(&response.body) {
+        Some(url) => format!(
+            "{}, too large to attach: {}",
+            summary,
+            ctx.data().uploader.download_link(&url, EXPORT_FILE_NAME)
+        ),
+        None => {
+            tracing::error!(
+                "Failed to create download link. Response:\n{}",
+                response.body
+            );
+            "Failed to create download link".to_owned()
+        }
+    };
+    reply::send_text(ctx, reply).await?;
+
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use crate::archive::{ArchiveOptions, AttachmentSource, HttpSource, Submission, clip_count};
+use crate::commands::pull::{self, MAX_DIRECT_ATTACHMENT_BYTES};
+use crate::data::{Context, Error};
+use crate::error::BotError;
+use crate::reply;
+use anyhow::Context as AnyhowContext;
+use poise::serenity_prelude::{self as serenity, ChannelId, MessageId};
+use std::io::Read;
+use tracing::{Span, instrument};
+
+#[poise::command(slash_command, guild_only)]
+#[instrument(name = "grab", skip_all, fields(
+    id = ctx.id(),
+    username = ctx.author().name,
+    message = message,
+    submissions = tracing::field::Empty,
+    archive_size = tracing::field::Empty,
+    upload_success = tracing::field::Empty,
+    link_host = tracing::field::Empty,
+))]
+pub async fn grab(
+    ctx: Context<'_>,
+    #[description = "Link or ID of a message in this channel"] message: String,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral()
+        .await
+        .context("Failed to defer response")?;
+
+    let Some((channel_id, message_id)) = parse_message_link(&message) else {
+        reply::send_text(
+            ctx,
+            "Use a message link (right click the message, Copy Message Link) or the message ID",
+        )
+        .await?;
+        return Ok(());
+    };
+    if channel_id.is_some_and(|channel_id| channel_id != ctx.channel_id()) {
+        reply::send_text(ctx, "The message must be in this channel").await?;
+        return Ok(());
+    }
+
+    let message = match ctx.channel_id().message(ctx, message_id).await {
+        Ok(message) => message,
+        Err(serenity::Error::Http(e))
+            if e.status_code() == Some(serenity::StatusCode::NOT_FOUND) =>
+        {
+            reply::send_text(ctx, "Message not found in this channel").await?;
+            return Ok(());
+        }
+        Err(e) => {
+            return Err(BotError::Scan(
+                anyhow::Error::new(e).context("Failed to get message"),
+            ));
+        }
+    };
+
+    let config = ctx.data().config.load_full();
+    let submissions: Vec<Submission> = message
+        .attachments
+        .iter()
+        .filter(|attachment| config.media_filter.accepts(attachment))
+        .map(|attachment| Submission {
+            attachment: attachment.clone(),
+            channel_id: message.channel_id,
+            message_id: message.id,
+            folder: None,
+            move_name: None,
+            username: message.author.name.clone(),
+            text: message.content.clone(),
+        })
+        .collect();
+    if submissions.is_empty() {
+        reply::send_text(ctx, "No video (.mov or .mp4) on that message").await?;
+        return Ok(());
+    }
+    Span::current().record("submissions", submissions.len());
+
+    // small enough to attach as they are, without zipping or uploading them
+    let total_size: u64 = submissions
+        .iter()
+        .map(|submission| u64::from(submission.attachment.size))
+        .sum();
+    if total_size <= MAX_DIRECT_ATTACHMENT_BYTES {
+        let attachment_timeout = config.attachment_timeout;
+        let user_agent = config.download_user_agent.clone();
+        let attachments = submissions
+            .iter()
+            .map(|submission| submission.attachment.clone())
+            .collect::<Vec<_>>();
+        let files = tokio::task::spawn_blocking(move || {
+            let source = HttpSource::new(attachment_timeout, &user_agent)?;
+            attachments
+                .into_iter()
+                .map(|attachment| {
+                    let mut file = Vec::new();
+                    source
+                        .fetch(&attachment)?
+                        .read_to_end(&mut file)
+                        .context(format!("Failed to download attachment {}", attachment.id))?;
+                    anyhow::ensure!(
+                        file.len() as u64 == u64::from(attachment.size),
+                        "Attachment {} is incomplete, got {} of {} bytes",
+                        attachment.id,
+                        file.len(),
+                        attachment.size
+                    );
+                    Ok((file, attachment.filename))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        })
+        .await
+        .context("Download task failed")
+        .and_then(|result| result)
+        .map_err(BotError::Archive)?;
+
+        reply::send_files(
+            ctx,
+            format!("{} from {}", clip_count(files.len()), message.author.name),
+            files,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let name = format!("grab-{}", message.id);
+    let zip_file_name = format!("{}-{}.zip", message.author.name, message.id);
+    let options = ArchiveOptions {
+        include_text: false,
+        thumbnails: false,
+        max_file_size: None,
+        oldest_first: false,
+        numbered: false,
+        preserve_names: false,
+        compression: config.archive_compression,
+        stored_extensions: config.stored_extensions.clone(),
+        transcode: None,
+        duration: None,
+        download_concurrency: config.download_concurrency,
+        download_delay: config.download_delay,
+    };
+
+    let dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let (dir, archive_result) = pull::build_archive(
+        ctx,
+        &config,
+        dir,
+        zip_file_name.clone(),
+        &name,
+        submissions,
+        Vec::new(),
+        None,
+        options,
+    )
+    .await?;
+    if !archive_result.notes.is_empty() {
+        reply::send_text(ctx, archive_result.notes.join("\n")).await?;
+    }
+
+    pull::deliver(
+        ctx,
+        name.clone(),
+        std::slice::from_ref(&name),
+        archive_result.archive,
+        &zip_file_name,
+        archive_result.clips,
+        None,
+        false,
+    )
+    .await?;
+
+    dir.close()
+        .context("Failed to close and remove temporary directory")?;
+
+    Ok(())
+}
+
+/// Channel and message of a message link, or only the message of a message ID
+fn parse_message_link(input: &str) -> Option<(Option<ChannelId>, MessageId)> {
+    let input = input.trim();
+    if let Ok(id) = input.parse::<u64>() {
+        return (id != 0).then(|| (None, MessageId::new(id)));
+    }
+
+    // https://discord.com/channels/<guild>/<channel>/<message>
+    let mut ids = input
+        .trim_end_matches('/')
+        .rsplit('/')
+        .map(|id| id.parse::<u64>().ok().filter(|&id| id != 0));
+    let message_id = ids.next()??;
+    let channel_id = ids.next()??;
+    Some((Some(ChannelId::new(channel_id)), MessageId::new(message_id)))
+}
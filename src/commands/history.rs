@@ -0,0 +1,64 @@
+use crate::data::{Context, Error};
+use crate::reply;
+use tracing::instrument;
+
+/// Uploads listed by `/history`
+const MAX_HISTORY_ENTRIES: usize = 10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, poise::ChoiceParameter)]
+pub enum HistoryScope {
+    #[default]
+    #[name = "mine"]
+    Mine,
+    #[name = "server"]
+    Server,
+}
+
+#[poise::command(slash_command)]
+#[instrument(name = "history", skip_all, fields(id = ctx.id(), username = ctx.author().name))]
+pub async fn history(
+    ctx: Context<'_>,
+    #[description = "Your pulls (default) or everyone's in this server"] scope: Option<
+        HistoryScope,
+    >,
+) -> Result<(), Error> {
+    let scope = scope.unwrap_or_default();
+    let user_id = ctx.author().id;
+    let guild_id = ctx.guild_id();
+
+    let entries: Vec<String> = ctx
+        .data()
+        .history
+        .lock()
+        .unwrap()
+        .recent()
+        .filter(|record| match scope {
+            HistoryScope::Mine => record.user_id == user_id,
+            HistoryScope::Server => guild_id.is_some() && record.guild_id == guild_id,
+        })
+        .take(MAX_HISTORY_ENTRIES)
+        .map(|record| {
+            let status = if record.is_expired() {
+                "**expired**".to_owned()
+            } else {
+                format!("expires <t:{}:R>", record.expires_at())
+            };
+            format!(
+                "`{}` <t:{}:R>: <{}> ({})",
+                record.moves.join("`, `"),
+                record.uploaded_at.unix_timestamp(),
+                record.link,
+                status
+            )
+        })
+        .collect();
+
+    let reply = if entries.is_empty() {
+        "No recent pulls found, history is kept since the bot last started".to_owned()
+    } else {
+        format!("Recent pulls:\n{}", entries.join("\n"))
+    };
+    reply::send_text(ctx, reply).await?;
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use crate::data::{Context, Error};
+use crate::reply;
+use std::sync::atomic::Ordering;
+use tracing::instrument;
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    required_permissions = "ADMINISTRATOR"
+)]
+#[instrument(name = "metrics", skip_all, fields(id = ctx.id(), username = ctx.author().name))]
+pub async fn metrics(ctx: Context<'_>) -> Result<(), Error> {
+    let metrics = &ctx.data().metrics;
+
+    let reply = format!(
+        "```\npulls_total          {}\nbytes_uploaded_total {}\nsearches_total       {}\nfailures_total       {}\n```",
+        metrics.pulls_served.load(Ordering::Relaxed),
+        metrics.bytes_uploaded.load(Ordering::Relaxed),
+        metrics.searches.load(Ordering::Relaxed),
+        metrics.failures.load(Ordering::Relaxed)
+    );
+
+    reply::send_text(ctx, reply).await?;
+
+    Ok(())
+}
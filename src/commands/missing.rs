@@ -0,0 +1,119 @@
+use crate::config::MediaFilter;
+use crate::data::{Context, Error};
+use crate::error::BotError;
+use crate::moves::{self, MOVE_LIST_NOT_CONFIGURED};
+use crate::reply;
+use crate::scan::{self, MessagePages};
+use anyhow::Context as AnyhowContext;
+use poise::serenity_prelude::{self as serenity, MessageId};
+use std::collections::HashMap;
+use tracing::instrument;
+
+/// Moves listed per page of the reply
+const PAGE_SIZE: usize = 50;
+
+#[poise::command(slash_command, guild_only)]
+#[instrument(name = "missing", skip_all, fields(id = ctx.id(), username = ctx.author().name, forum = forum.name, page = page))]
+pub async fn missing(
+    ctx: Context<'_>,
+    #[description = "Forum with a post per move"]
+    #[channel_types("Forum")]
+    forum: serenity::GuildChannel,
+    #[description = "Page of the list, 50 moves each"]
+    #[min = 1]
+    page: Option<usize>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral()
+        .await
+        .context("Failed to defer response")?;
+
+    let move_list = ctx.data().move_list.load_full();
+    if move_list.is_empty() {
+        reply::send_text(ctx, MOVE_LIST_NOT_CONFIGURED).await?;
+        return Ok(());
+    }
+
+    let media = ctx.data().config.load().media_filter.clone();
+    let threads = scan::forum_threads(ctx.http(), &forum)
+        .await
+        .map_err(BotError::Scan)?;
+
+    // a move is covered once any of its posts has a video
+    let mut covered: HashMap<&str, bool> = HashMap::new();
+    for thread in &threads {
+        let Some(move_name) = moves::thread_move(&move_list, &thread.name) else {
+            continue;
+        };
+        ctx.data().remember_move_thread(move_name, thread.id);
+        if covered.get(move_name).copied().unwrap_or(false) {
+            continue;
+        }
+        let has_video = has_video(ctx, thread, &media).await?;
+        *covered.entry(move_name).or_default() |= has_video;
+    }
+
+    let missing: Vec<String> = move_list
+        .iter()
+        .filter_map(|move_name| match covered.get(move_name.as_str()) {
+            Some(true) => None,
+            Some(false) => Some(format!("{} (no videos)", move_name)),
+            None => Some(format!("{} (no post)", move_name)),
+        })
+        .collect();
+
+    if missing.is_empty() {
+        reply::send_text(ctx, format!("Every move has a video in <#{}>", forum.id)).await?;
+        return Ok(());
+    }
+
+    let pages = missing.len().div_ceil(PAGE_SIZE);
+    let page = page.unwrap_or(1).clamp(1, pages);
+    let mut reply = format!(
+        "{} of {} moves have no video in <#{}>, page {} of {}:\n{}",
+        missing.len(),
+        move_list.len(),
+        forum.id,
+        page,
+        pages,
+        missing
+            .iter()
+            .skip((page - 1) * PAGE_SIZE)
+            .take(PAGE_SIZE)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    if pages > 1 {
+        reply.push_str("\nUse `/missing` with `page` to see the other pages");
+    }
+
+    reply::send_text_or_file(ctx, reply, "missing-moves.txt").await?;
+
+    Ok(())
+}
+
+/// Whether any message in `thread` has a video, stops at the first one
+async fn has_video(
+    ctx: Context<'_>,
+    thread: &serenity::GuildChannel,
+    media: &MediaFilter,
+) -> Result<bool, Error> {
+    let mut pages = MessagePages::new(
+        ctx.serenity_context().http.clone(),
+        thread.id,
+        MessageId::new(thread.id.get()),
+        thread.last_message_id,
+        1,
+    );
+
+    while let Some(messages) = pages.next().await.map_err(BotError::Scan)? {
+        let found = messages
+            .iter()
+            .flat_map(|message| &message.attachments)
+            .any(|attachment| media.accepts(attachment));
+        if found {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,80 @@
+use crate::data::{Context, Error};
+use crate::reply;
+use poise::serenity_prelude::Permissions;
+use tracing::instrument;
+
+/// Permissions the bot needs in the channels it's used in, with what they're needed for
+const REQUIRED_PERMISSIONS: [(Permissions, &str, &str); 4] = [
+    (
+        Permissions::VIEW_CHANNEL,
+        "View Channel",
+        "to see the channel and its threads",
+    ),
+    (
+        Permissions::READ_MESSAGE_HISTORY,
+        "Read Message History",
+        "to find the clips in a thread",
+    ),
+    (
+        Permissions::SEND_MESSAGES,
+        "Send Messages",
+        "for `post_to_thread` and replies to pulls that take longer than 14 minutes",
+    ),
+    (
+        Permissions::ATTACH_FILES,
+        "Attach Files",
+        "to attach small archives, playlists and long replies",
+    ),
+];
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    required_permissions = "ADMINISTRATOR"
+)]
+#[instrument(name = "perms", skip_all, fields(id = ctx.id(), username = ctx.author().name))]
+pub async fn perms(ctx: Context<'_>) -> Result<(), Error> {
+    // Discord sends the bot's permissions in the channel with the command
+    let permissions = match ctx {
+        poise::Context::Application(ctx) => ctx.interaction.app_permissions,
+        poise::Context::Prefix(_) => None,
+    };
+    let Some(permissions) = permissions else {
+        reply::send_text(
+            ctx,
+            "Discord didn't send my permissions in this channel, try again",
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let lines: Vec<String> = REQUIRED_PERMISSIONS
+        .iter()
+        .map(|(permission, name, needed_for)| {
+            if permissions.contains(*permission) {
+                format!("{}: granted", name)
+            } else {
+                format!("{}: missing, needed {}", name, needed_for)
+            }
+        })
+        .collect();
+    let missing = REQUIRED_PERMISSIONS
+        .iter()
+        .filter(|(permission, _, _)| !permissions.contains(*permission))
+        .count();
+
+    let summary = if missing == 0 {
+        format!("I have every permission I need in <#{}>", ctx.channel_id())
+    } else {
+        format!(
+            "I'm missing {} permission{} in <#{}>",
+            missing,
+            if missing == 1 { "" } else { "s" },
+            ctx.channel_id()
+        )
+    };
+    reply::send_text(ctx, format!("{}:\n{}", summary, lines.join("\n"))).await?;
+
+    Ok(())
+}
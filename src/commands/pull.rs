@@ -0,0 +1,1595 @@
+use crate::antivirus::{self, Verdict};
+use crate::archive::{
+    self, ArchiveOptions, ArchiveResult, Compression, DurationFilter, HttpSource, Submission,
+};
+use crate::config::{Config, MediaFilter};
+use crate::data::{ActivePull, Context, Error, Metrics};
+use crate::error::BotError;
+use crate::history::{PullMarkerKey, UploadKey, UploadRecord};
+use crate::moves;
+use crate::quota::UploadQuota;
+use crate::reply;
+use crate::scan::{self, MessagePages};
+use crate::upload;
+use crate::webhook::{self, Completion};
+use anyhow::Context as AnyhowContext;
+use poise::serenity_prelude::{
+    self as serenity, Attachment, AttachmentId, ChannelType, CreateEmbed, CreateEmbedFooter,
+    GuildChannel, Message, MessageId, ReactionType, Timestamp, UserId,
+};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tempfile::TempDir;
+use tracing::{Span, instrument};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, poise::ChoiceParameter)]
+pub enum SortOrder {
+    #[default]
+    #[name = "newest"]
+    Newest,
+    #[name = "oldest"]
+    Oldest,
+}
+
+/// Whether `/pull` also sends a playlist of the clips' links
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter)]
+pub enum Playlist {
+    #[name = "also"]
+    Also,
+    /// Only the playlist, nothing is downloaded
+    #[name = "only"]
+    Only,
+}
+
+// every option of the slash command is a parameter
+#[allow(clippy::too_many_arguments)]
+#[poise::command(slash_command)]
+#[instrument(name = "pull", skip_all, fields(
+    id = ctx.id(),
+    username = ctx.author().name,
+    move_name = move_name,
+    submissions = tracing::field::Empty,
+    archive_size = tracing::field::Empty,
+    upload_success = tracing::field::Empty,
+    link_host = tracing::field::Empty,
+))]
+pub async fn pull(
+    ctx: Context<'_>,
+    #[description = "Move name"] move_name: String,
+    #[description = "Save the text of each clip's message next to it"] include_text: Option<bool>,
+    #[description = "Only archive videos posted since your last pull of this move here"]
+    since_last: Option<bool>,
+    #[description = "Only archive these file extensions, separated by commas, e.g. mp4"]
+    extensions: Option<String>,
+    #[description = "Order of the clips in the archive, newest (default) or oldest first"]
+    sort: Option<SortOrder>,
+    #[description = "Prefix file names with their position in the archive"] numbered: Option<bool>,
+    #[description = "File name of the archive instead of the move name, e.g. week3-review"]
+    #[max_length = 100]
+    archive_name: Option<String>,
+    #[description = "Compression of the files in the archive, zstd needs a recent unzip tool"]
+    compression: Option<Compression>,
+    #[description = "Only archive this user's videos"] user: Option<serenity::User>,
+    #[description = "Leave out this user's videos"] exclude_user: Option<serenity::User>,
+    #[description = "Post the link so everyone in the channel can see it"] public: Option<bool>,
+    #[description = "Archive every post of this forum with this tag, in a folder per post"]
+    tag: Option<String>,
+    #[description = "Also post the link as a message in this thread"] post_to_thread: Option<bool>,
+    #[description = "Re-encode the clips to make the archive smaller, at some loss of quality"]
+    transcode: Option<bool>,
+    #[description = "Leave out clips shorter than this many seconds"]
+    #[min = 0]
+    min_seconds: Option<f64>,
+    #[description = "Leave out clips longer than this many seconds"]
+    #[min = 0]
+    max_seconds: Option<f64>,
+    #[description = "Make a separate archive for each file extension, e.g. one of mp4s and one of movs"]
+    split_by_extension: Option<bool>,
+    #[description = "Leave out clips already archived by an earlier pull of this move"]
+    skip_archived: Option<bool>,
+    #[description = "Only archive clips of messages with this reaction"] reaction: Option<String>,
+    #[description = "Only archive clips of messages with at least this many reactions in total"]
+    #[min = 1]
+    min_reactions: Option<u64>,
+    #[description = "Add a thread-info.txt with the thread's name, creation date and participants"]
+    thread_info: Option<bool>,
+    #[description = "Only archive clips posted within this long from now, e.g. 24h, 7d or 2w"]
+    within: Option<String>,
+    #[description = "Archive clips posted by bots and webhooks, INCLUDE_BOTS by default"]
+    include_bots: Option<bool>,
+    #[description = "Also send an .m3u playlist of the clip links to stream them, or only the playlist"]
+    playlist: Option<Playlist>,
+    #[description = "Keep the clips' original file names, adding the attachment ID to duplicates"]
+    preserve_names: Option<bool>,
+    #[description = "Reply right away and DM you the link when the archive is ready"]
+    background: Option<bool>,
+    #[description = "Keep only each user's largest clip, e.g. their best take"]
+    largest_per_user: Option<bool>,
+) -> Result<(), Error> {
+    let public = public.unwrap_or(false);
+    // the thinking indicator has the visibility of the reply that replaces it
+    if public {
+        ctx.defer().await
+    } else {
+        ctx.defer_ephemeral().await
+    }
+    .context("Failed to defer response")?;
+
+    if let (Some(min), Some(max)) = (min_seconds, max_seconds)
+        && min > max
+    {
+        reply::send_text(ctx, "`min_seconds` must not be more than `max_seconds`").await?;
+        return Ok(());
+    }
+
+    let media_filter = ctx.data().config.load().media_filter.clone();
+    let extensions =
+        match parse_extensions(extensions.as_deref().unwrap_or_default(), &media_filter) {
+            Ok(extensions) => extensions,
+            Err(unsupported) => {
+                reply::send_text(
+                    ctx,
+                    format!(
+                        "Unsupported extensions: {}. Supported extensions are {}",
+                        unsupported.join(", "),
+                        media_filter.extensions.join(", ")
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+        };
+
+    let within = match within.as_deref() {
+        Some(input) => match parse_window(input) {
+            Some(window) => Some((input.trim().to_lowercase(), window)),
+            None => {
+                reply::send_text(
+                    ctx,
+                    "`within` must be a number of hours, days or weeks, e.g. 24h, 7d or 2w",
+                )
+                .await?;
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+
+    let reaction = match reaction.as_deref().map(str::trim) {
+        Some(reaction) => match parse_reaction(reaction) {
+            Some(reaction) => Some(reaction),
+            None => {
+                reply::send_text(ctx, "`reaction` must be an emoji, e.g. ✅").await?;
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+
+    // registered until the pull returns, so a shutdown waits for the promised DM
+    let _background_pull = if background.unwrap_or(false) {
+        let Some(background_pull) = ctx.data().background_pulls.start(
+            ctx.id(),
+            format!("{} pulling {}", ctx.author().name, move_name),
+        ) else {
+            reply::send_text(
+                ctx,
+                "I'm restarting, try again in a minute or pull without `background`",
+            )
+            .await?;
+            return Ok(());
+        };
+        let reply = "Your archive is being prepared, I'll DM you the link when it's ready";
+        if public {
+            reply::send_public_text(ctx, reply).await?;
+        } else {
+            reply::send_text(ctx, reply).await?;
+        }
+        reply::reply_directly(ctx).await;
+        Some(background_pull)
+    } else {
+        None
+    };
+
+    run(
+        ctx,
+        PullOptions {
+            move_name,
+            include_text: include_text.unwrap_or(false),
+            since_last: since_last.unwrap_or(false),
+            extensions,
+            sort: sort.unwrap_or_default(),
+            numbered: numbered.unwrap_or(false),
+            archive_name: archive_name.as_deref().and_then(sanitize_archive_name),
+            compression,
+            user: user.map(|user| user.id),
+            exclude_user: exclude_user.map(|user| user.id),
+            public,
+            tag,
+            post_to_thread: post_to_thread.unwrap_or(false),
+            transcode: transcode.unwrap_or(false),
+            min_seconds,
+            max_seconds,
+            split_by_extension: split_by_extension.unwrap_or(false),
+            skip_archived: skip_archived.unwrap_or(false),
+            reaction,
+            min_reactions,
+            thread_info: thread_info.unwrap_or(false),
+            within,
+            include_bots,
+            playlist,
+            largest_per_user: largest_per_user.unwrap_or(false),
+            preserve_names: preserve_names.unwrap_or(false),
+        },
+    )
+    .await
+}
+
+pub(super) struct PullOptions {
+    pub move_name: String,
+    pub include_text: bool,
+    pub since_last: bool,
+    /// Lowercase extensions to keep, all allowed extensions when empty
+    pub extensions: Vec<String>,
+    pub sort: SortOrder,
+    pub numbered: bool,
+    /// Sanitized file name of the archive, `<move_name>.zip` when unset
+    pub archive_name: Option<String>,
+    /// Compression of the archive, `ARCHIVE_COMPRESSION` when unset
+    pub compression: Option<Compression>,
+    /// Only this user's videos are archived
+    pub user: Option<UserId>,
+    /// This user's videos are left out, even if it's also `user`
+    pub exclude_user: Option<UserId>,
+    /// Post the link visibly, the response must have been deferred publicly
+    pub public: bool,
+    /// Name of a forum tag, the posts of the thread's forum with it are archived instead of the thread
+    pub tag: Option<String>,
+    /// Also post the link as a normal message in the thread the pull was run in
+    pub post_to_thread: bool,
+    /// Re-encode the clips with the `TRANSCODE_*` settings
+    pub transcode: bool,
+    /// Only clips at least this long are archived
+    pub min_seconds: Option<f64>,
+    /// Only clips at most this long are archived
+    pub max_seconds: Option<f64>,
+    /// One archive per file extension, each with its own size limit
+    pub split_by_extension: bool,
+    /// Leave out the clips in the clip registry for the move
+    pub skip_archived: bool,
+    /// Only clips of messages with this reaction are archived
+    pub reaction: Option<ReactionType>,
+    /// Only clips of messages with at least this many reactions of any emoji are archived
+    pub min_reactions: Option<u64>,
+    /// Add a `thread-info.txt` describing the scanned threads
+    pub thread_info: bool,
+    /// Only clips posted this long before now are archived, with the window as the user wrote it
+    pub within: Option<(String, Duration)>,
+    /// Archive clips posted by bots, `INCLUDE_BOTS` when unset
+    pub include_bots: Option<bool>,
+    /// Send an `.m3u` playlist of the clips' Discord links, alongside or instead of the archive
+    pub playlist: Option<Playlist>,
+    /// Only the largest clip of each submitter is archived
+    pub largest_per_user: bool,
+    /// Name the files in the archive after the clips' original names
+    pub preserve_names: bool,
+}
+
+/// Which videos of the scanned messages are archived
+struct ScanFilter<'a> {
+    media: &'a MediaFilter,
+    extensions: &'a [String],
+    user: Option<UserId>,
+    exclude_user: Option<UserId>,
+    max_attachments: Option<usize>,
+    /// Messages up to this one are skipped without fetching them
+    posted_after: Option<MessageId>,
+    reaction: Option<&'a ReactionType>,
+    min_reactions: Option<u64>,
+    /// Clips posted by bots are left out and counted when false
+    include_bots: bool,
+    /// Clips of earlier archives, left out and counted without counting against the limit
+    already_archived: HashSet<AttachmentId>,
+}
+
+impl ScanFilter<'_> {
+    fn keeps(&self, message: &Message, attachment: &Attachment) -> bool {
+        self.user.is_none_or(|user| message.author.id == user)
+            && self
+                .exclude_user
+                .is_none_or(|user| message.author.id != user)
+            && self.media.accepts(attachment)
+            && (self.extensions.is_empty() || has_extension(&attachment.filename, self.extensions))
+            && self.reaction.is_none_or(|wanted| {
+                message
+                    .reactions
+                    .iter()
+                    .any(|reaction| same_emoji(&reaction.reaction_type, wanted))
+            })
+            && self.min_reactions.is_none_or(|min| {
+                message
+                    .reactions
+                    .iter()
+                    .map(|reaction| reaction.count)
+                    .sum::<u64>()
+                    >= min
+            })
+    }
+}
+
+/// Emoji of the `reaction` option, a unicode emoji or a custom one like `<:name:id>`.
+/// Serenity takes any text as a unicode emoji, but no emoji is plain ASCII like `good` is
+fn parse_reaction(input: &str) -> Option<ReactionType> {
+    match ReactionType::try_from(input).ok()? {
+        ReactionType::Unicode(emoji) if emoji.is_ascii() => None,
+        reaction => Some(reaction),
+    }
+}
+
+/// Whether `reaction` is the emoji `wanted`, custom emojis by id since they can be renamed
+fn same_emoji(reaction: &ReactionType, wanted: &ReactionType) -> bool {
+    match (reaction, wanted) {
+        (ReactionType::Custom { id, .. }, ReactionType::Custom { id: wanted, .. }) => id == wanted,
+        // with or without the emoji presentation selector, which clients add inconsistently
+        (ReactionType::Unicode(emoji), ReactionType::Unicode(wanted)) => {
+            emoji.trim_end_matches('\u{fe0f}') == wanted.trim_end_matches('\u{fe0f}')
+        }
+        _ => false,
+    }
+}
+
+struct ThreadScan {
+    newest_message_id: Option<MessageId>,
+    /// The attachment limit was reached, later threads aren't scanned
+    limit_reached: bool,
+    /// Clips left out because they were archived before
+    already_archived: usize,
+    /// Clips left out because a bot posted them
+    from_bots: usize,
+    messages_scanned: usize,
+    /// Usernames of everyone who posted in the scanned messages
+    authors: BTreeSet<String>,
+}
+
+/// Keeps the largest clip by bytes of each submitter, the newest one of equally large clips.
+/// Returns how many clips were left out
+fn keep_largest_per_user(submissions: &mut Vec<Submission>) -> usize {
+    let mut largest: HashMap<String, (u32, AttachmentId)> = HashMap::new();
+    for submission in submissions.iter() {
+        let size = submission.attachment.size;
+        let entry = largest
+            .entry(submission.username.clone())
+            .or_insert((size, submission.attachment.id));
+        if size > entry.0 || (size == entry.0 && submission.attachment.id > entry.1) {
+            *entry = (size, submission.attachment.id);
+        }
+    }
+
+    let before = submissions.len();
+    submissions.retain(|submission| {
+        largest
+            .get(&submission.username)
+            .is_some_and(|&(_, id)| id == submission.attachment.id)
+    });
+    before - submissions.len()
+}
+
+/// Extended M3U playlist of the submissions' attachment links in the order they were posted
+fn m3u_playlist(submissions: &[Submission]) -> String {
+    let mut submissions: Vec<&Submission> = submissions.iter().collect();
+    submissions.sort_by_key(|submission| submission.attachment.id);
+
+    let mut playlist = "#EXTM3U\n".to_owned();
+    for submission in submissions {
+        playlist.push_str(&format!(
+            "#EXTINF:-1,{} - {}\n{}\n",
+            submission.username, submission.attachment.filename, submission.attachment.url
+        ));
+    }
+    playlist
+}
+
+/// Parses a comma separated list of extensions, returns the unsupported ones on failure
+fn parse_extensions(input: &str, media: &MediaFilter) -> Result<Vec<String>, Vec<String>> {
+    let (extensions, unsupported): (Vec<String>, Vec<String>) = input
+        .split(',')
+        .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .partition(|ext| media.allows_extension(ext));
+
+    if unsupported.is_empty() {
+        Ok(extensions)
+    } else {
+        Err(unsupported)
+    }
+}
+
+/// Hash of what ends up in the archive, equal for pulls that would upload the same archive
+fn contents_hash(submissions: &[Submission], zip_file_name: &str, options: &ArchiveOptions) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for submission in submissions {
+        submission.attachment.id.hash(&mut hasher);
+    }
+    zip_file_name.hash(&mut hasher);
+    options.include_text.hash(&mut hasher);
+    options.thumbnails.hash(&mut hasher);
+    options.numbered.hash(&mut hasher);
+    options.preserve_names.hash(&mut hasher);
+    options.compression.hash(&mut hasher);
+    options.stored_extensions.hash(&mut hasher);
+    options.transcode.is_some().hash(&mut hasher);
+    if let Some(duration) = options.duration {
+        duration.min_seconds.map(f64::to_bits).hash(&mut hasher);
+        duration.max_seconds.map(f64::to_bits).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Window like `24h`, `7d` or `2w`, `None` if it isn't one or is empty
+fn parse_window(input: &str) -> Option<Duration> {
+    let input = input.trim().to_lowercase();
+    let unit_seconds = match input.chars().last()? {
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    let count: u64 = input[..input.len() - 1].trim().parse().ok()?;
+    if count == 0 {
+        return None;
+    }
+    count.checked_mul(unit_seconds).map(Duration::from_secs)
+}
+
+/// Id of a message that would have been posted at `time`, older messages have smaller ids
+fn snowflake_at(time: SystemTime) -> MessageId {
+    const DISCORD_EPOCH_MILLIS: u64 = 1_420_070_400_000;
+    let millis = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    MessageId::new(millis.saturating_sub(DISCORD_EPOCH_MILLIS).max(1) << 22)
+}
+
+/// Archive file name made of safe characters and ending in `.zip`, `None` if nothing is left
+fn sanitize_archive_name(input: &str) -> Option<String> {
+    let input = input.trim();
+    let stem = input
+        .len()
+        .checked_sub(4)
+        .filter(|&i| input.is_char_boundary(i) && input[i..].eq_ignore_ascii_case(".zip"))
+        .map_or(input, |i| &input[..i]);
+
+    let stem = archive::sanitize_file_stem(stem);
+    (!stem.is_empty()).then(|| format!("{}.zip", stem))
+}
+
+/// Folder of the archive a thread's clips go in, named after the thread and unique in `used`
+fn thread_folder(used: &mut HashSet<String>, thread: &GuildChannel) -> String {
+    let name = archive::sanitize_file_stem(thread.name.trim());
+    if !name.is_empty() && used.insert(name.clone()) {
+        return name;
+    }
+    let name = format!("{}-{}", name, thread.id)
+        .trim_start_matches('-')
+        .to_owned();
+    used.insert(name.clone());
+    name
+}
+
+/// Thread the command was run in, `None` after replying why it can't be scanned
+pub(super) async fn current_thread(ctx: Context<'_>) -> Result<Option<GuildChannel>, Error> {
+    if ctx.guild_id().is_none() {
+        reply::send_text(ctx, "This command only works in server threads, not DMs").await?;
+        return Ok(None);
+    }
+
+    let guild_channel = match ctx.guild_channel().await {
+        Some(gc) => match gc.kind {
+            ChannelType::NewsThread | ChannelType::PublicThread | ChannelType::PrivateThread => gc,
+            _ => {
+                reply::send_text(ctx, "This command must be run in a thread").await?;
+                return Ok(None);
+            }
+        },
+        None => {
+            reply::send_text(ctx, "This command must be run in a thread").await?;
+            return Ok(None);
+        }
+    };
+
+    // private threads look empty instead of failing to the bots that aren't in them
+    if guild_channel.kind == ChannelType::PrivateThread
+        && !is_thread_member(ctx, &guild_channel).await?
+    {
+        reply::send_text(
+            ctx,
+            "I'm not a member of this private thread, add me and try again",
+        )
+        .await?;
+        return Ok(None);
+    }
+
+    Ok(Some(guild_channel))
+}
+
+/// Videos in `thread` a `/pull` without options would archive, with notes like the attachment
+/// limit being reached
+pub(super) async fn thread_clips(
+    ctx: Context<'_>,
+    config: &Config,
+    thread: &GuildChannel,
+) -> Result<(Vec<Submission>, Vec<String>), Error> {
+    let filter = ScanFilter {
+        media: &config.media_filter,
+        extensions: &[],
+        user: None,
+        exclude_user: None,
+        max_attachments: config.max_attachments,
+        posted_after: None,
+        reaction: None,
+        min_reactions: None,
+        include_bots: config.include_bots,
+        already_archived: HashSet::new(),
+    };
+    let mut submissions = Vec::new();
+    let mut notes = Vec::new();
+    scan_thread(
+        ctx,
+        thread,
+        None,
+        None,
+        &filter,
+        &mut submissions,
+        &mut notes,
+    )
+    .await?;
+    Ok((submissions, notes))
+}
+
+/// Whether the bot was added to `thread`
+pub(super) async fn is_thread_member(
+    ctx: Context<'_>,
+    thread: &GuildChannel,
+) -> Result<bool, Error> {
+    match thread
+        .id
+        .get_thread_member(ctx, ctx.framework().bot_id, false)
+        .await
+    {
+        Ok(_) => Ok(true),
+        Err(serenity::Error::Http(e))
+            if e.status_code().is_some_and(|status| {
+                status == serenity::StatusCode::NOT_FOUND
+                    || status == serenity::StatusCode::FORBIDDEN
+            }) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(BotError::Scan(
+            anyhow::Error::new(e).context("Failed to check thread membership"),
+        )),
+    }
+}
+
+/// Threads of the forum `thread` is in with the tag `tag_name`, newest first.
+/// `None` after replying why there aren't any to scan
+async fn tagged_threads(
+    ctx: Context<'_>,
+    thread: &GuildChannel,
+    tag_name: &str,
+) -> Result<Option<Vec<GuildChannel>>, Error> {
+    let forum = match thread.parent_id {
+        Some(parent_id) => parent_id
+            .to_channel(ctx)
+            .await
+            .context("Failed to get forum")
+            .map_err(BotError::Scan)?
+            .guild()
+            .filter(|channel| channel.kind == ChannelType::Forum),
+        None => None,
+    };
+    let Some(forum) = forum else {
+        reply::send_text(ctx, "The `tag` option must be used in a forum post").await?;
+        return Ok(None);
+    };
+
+    let Some(tag) = forum
+        .available_tags
+        .iter()
+        .find(|tag| tag.name.eq_ignore_ascii_case(tag_name.trim()))
+    else {
+        let reply = if forum.available_tags.is_empty() {
+            "Tag not found, this forum has no tags".to_owned()
+        } else {
+            format!(
+                "Tag not found, this forum's tags are {}",
+                forum
+                    .available_tags
+                    .iter()
+                    .map(|tag| format!("`{}`", tag.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        reply::send_text(ctx, reply).await?;
+        return Ok(None);
+    };
+
+    let threads: Vec<GuildChannel> = scan::forum_threads(ctx.http(), &forum)
+        .await
+        .map_err(BotError::Scan)?
+        .into_iter()
+        .filter(|thread| thread.applied_tags.contains(&tag.id))
+        .collect();
+
+    if threads.is_empty() {
+        reply::send_text(ctx, format!("No post tagged `{}` found", tag.name)).await?;
+        return Ok(None);
+    }
+    Ok(Some(threads))
+}
+
+fn has_extension(file_name: &str, extensions: &[String]) -> bool {
+    Path::new(file_name)
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .is_some_and(|ext| extensions.contains(&ext.to_lowercase()))
+}
+
+/// Scans the current thread and uploads an archive of its videos, the response must already be deferred
+pub(super) async fn run(ctx: Context<'_>, options: PullOptions) -> Result<(), Error> {
+    let PullOptions {
+        move_name,
+        include_text,
+        since_last,
+        extensions,
+        sort,
+        numbered,
+        archive_name,
+        compression,
+        user,
+        exclude_user,
+        public,
+        tag,
+        post_to_thread,
+        transcode,
+        min_seconds,
+        max_seconds,
+        split_by_extension,
+        skip_archived,
+        reaction,
+        min_reactions,
+        thread_info,
+        within,
+        include_bots,
+        playlist,
+        largest_per_user,
+        preserve_names,
+    } = options;
+    let config = ctx.data().config.load_full();
+    let _active_pull = ActivePull::new(&ctx.data().metrics.active_pulls);
+
+    let Some(guild_channel) = current_thread(ctx).await? else {
+        return Ok(());
+    };
+
+    let move_list = ctx.data().move_list.load_full();
+    if move_list.is_empty() {
+        reply::send_text(ctx, moves::MOVE_LIST_NOT_CONFIGURED).await?;
+        return Ok(());
+    }
+
+    let Some(move_name) = moves::resolve(&move_list, &move_name).map(str::to_owned) else {
+        let suggestions = moves::closest_matches(&move_list, &move_name, 3);
+        let reply = if suggestions.is_empty() {
+            "Move not found, use `/search <page_number>` to get the move name".to_owned()
+        } else {
+            format!(
+                "Move not found, did you mean:\n{}\nOtherwise use `/search <page_number>` to get the move name",
+                suggestions
+                    .iter()
+                    .map(|suggestion| format!("`{}`", suggestion))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )
+        };
+        reply::send_text(ctx, reply).await?;
+        return Ok(());
+    };
+
+    let thread_id = guild_channel.id;
+    if tag.is_none() && guild_channel.kind == ChannelType::PublicThread {
+        ctx.data().remember_move_thread(&move_name, thread_id);
+    }
+    let threads = match &tag {
+        Some(tag) => match tagged_threads(ctx, &guild_channel, tag).await? {
+            Some(threads) => threads,
+            None => return Ok(()),
+        },
+        None => vec![guild_channel],
+    };
+
+    let mut submissions: Vec<Submission> = Vec::new();
+    let mut notes = Vec::new();
+    let filter = ScanFilter {
+        media: &config.media_filter,
+        extensions: &extensions,
+        user,
+        exclude_user,
+        max_attachments: config.max_attachments,
+        posted_after: within.as_ref().map(|(_, window)| {
+            snowflake_at(SystemTime::now().checked_sub(*window).unwrap_or(UNIX_EPOCH))
+        }),
+        reaction: reaction.as_ref(),
+        min_reactions,
+        include_bots: include_bots.unwrap_or(config.include_bots),
+        already_archived: if skip_archived {
+            ctx.data()
+                .clip_registry
+                .lock()
+                .unwrap()
+                .archived(&move_name)
+        } else {
+            HashSet::new()
+        },
+    };
+
+    let markers: Vec<_> = threads
+        .iter()
+        .map(|thread| {
+            let marker_key = PullMarkerKey {
+                user_id: ctx.author().id,
+                channel_id: thread.id,
+                move_name: move_name.clone(),
+            };
+            let marker = if since_last {
+                ctx.data().pull_markers.lock().unwrap().get(&marker_key)
+            } else {
+                None
+            };
+            (marker_key, marker)
+        })
+        .collect();
+    let any_marker = markers.iter().any(|(_, marker)| marker.is_some());
+    if since_last && !any_marker {
+        notes.push("No previous pull of this move found, pulling everything".to_owned());
+    }
+
+    let mut new_markers = Vec::new();
+    let mut folders = HashSet::new();
+    let mut already_archived = 0;
+    let mut from_bots = 0;
+    let mut thread_infos = Vec::new();
+    for (thread, (marker_key, marker)) in threads.iter().zip(markers) {
+        let folder = tag.is_some().then(|| thread_folder(&mut folders, thread));
+        let scan = scan_thread(
+            ctx,
+            thread,
+            marker,
+            folder,
+            &filter,
+            &mut submissions,
+            &mut notes,
+        )
+        .await?;
+        already_archived += scan.already_archived;
+        from_bots += scan.from_bots;
+        if thread_info {
+            thread_infos.push(describe_thread(thread, &scan));
+        }
+        if let Some(newest_message_id) = scan.newest_message_id {
+            new_markers.push((marker_key, newest_message_id));
+        }
+        if scan.limit_reached {
+            break;
+        }
+    }
+
+    if submissions.is_empty() {
+        let reply = if from_bots > 0 {
+            "Only bots posted videos (.mov or .mp4) here, use `include_bots` to archive them"
+                .to_owned()
+        } else if user.is_some() || exclude_user.is_some() {
+            "No video (.mov or .mp4) from the selected users found".to_owned()
+        } else if let Some((window, _)) = &within {
+            format!(
+                "No video (.mov or .mp4) posted within the last {} found",
+                window
+            )
+        } else if reaction.is_some() {
+            "No video (.mov or .mp4) on a message with that reaction found".to_owned()
+        } else if let Some(min) = min_reactions {
+            format!(
+                "No video (.mov or .mp4) on a message with at least {} reaction{} found",
+                min,
+                if min == 1 { "" } else { "s" }
+            )
+        } else if already_archived > 0 {
+            "Every video found was already archived by an earlier pull".to_owned()
+        } else if any_marker {
+            "No new video (.mov or .mp4) since your last pull".to_owned()
+        } else {
+            "No video (.mov or .mp4) found".to_owned()
+        };
+        reply::send_text(ctx, reply).await?;
+        return Ok(());
+    }
+
+    if from_bots > 0 {
+        notes.push(format!(
+            "Left out {} clip{} posted by bots",
+            from_bots,
+            if from_bots == 1 { "" } else { "s" }
+        ));
+    }
+
+    if already_archived > 0 {
+        notes.push(format!(
+            "Skipped {} clip{} already archived by an earlier pull",
+            already_archived,
+            if already_archived == 1 { "" } else { "s" }
+        ));
+    }
+
+    if largest_per_user {
+        let collapsed = keep_largest_per_user(&mut submissions);
+        if collapsed > 0 {
+            notes.push(format!(
+                "Kept only each user's largest clip, left out {} other clip{}",
+                collapsed,
+                if collapsed == 1 { "" } else { "s" }
+            ));
+        }
+    }
+
+    // each thread is scanned newest first, the posts of a tag one after the other
+    if threads.len() > 1 {
+        submissions.sort_by_key(|submission| Reverse(submission.message_id));
+    }
+    if sort == SortOrder::Oldest {
+        submissions.reverse();
+    }
+
+    if let Some(playlist) = playlist {
+        let stem = archive_name
+            .as_deref()
+            .map_or(move_name.as_str(), |name| name.trim_end_matches(".zip"));
+        let mut content = format!(
+            "Playlist of {} clip{}, oldest first. Discord's links expire about a day after they're fetched, so stream them soon",
+            submissions.len(),
+            if submissions.len() == 1 { "" } else { "s" }
+        );
+        if playlist == Playlist::Only && !notes.is_empty() {
+            content = format!("{}\n{}", notes.join("\n"), content);
+        }
+        reply::send_file(
+            ctx,
+            content,
+            m3u_playlist(&submissions).into_bytes(),
+            &format!("{}.m3u", stem),
+        )
+        .await?;
+        if playlist == Playlist::Only {
+            return Ok(());
+        }
+    }
+
+    let transcode = if transcode {
+        if config.transcode.is_none() {
+            notes.push(
+                "Transcoding isn't enabled on this bot, clips are archived as posted".to_owned(),
+            );
+        }
+        config.transcode
+    } else {
+        None
+    };
+
+    let duration = if min_seconds.is_some() || max_seconds.is_some() {
+        if !config.duration_filter {
+            notes.push(
+                "Filtering by length isn't enabled on this bot, clips of any length are archived"
+                    .to_owned(),
+            );
+        }
+        config.duration_filter.then_some(DurationFilter {
+            min_seconds,
+            max_seconds,
+        })
+    } else {
+        None
+    };
+
+    let mut dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let zip_file_name = archive_name.unwrap_or_else(|| format!("{}.zip", &move_name));
+
+    let options = ArchiveOptions {
+        include_text,
+        thumbnails: config.thumbnails,
+        max_file_size: config.max_file_size_bytes,
+        oldest_first: sort == SortOrder::Oldest,
+        numbered,
+        preserve_names,
+        compression: compression.unwrap_or(config.archive_compression),
+        stored_extensions: config.stored_extensions.clone(),
+        transcode,
+        duration,
+        download_concurrency: config.download_concurrency,
+        download_delay: config.download_delay,
+    };
+
+    let thread_info = thread_info.then(|| thread_infos.join("\n"));
+    Span::current().record("submissions", submissions.len());
+
+    let parts = if split_by_extension {
+        let stem = zip_file_name.trim_end_matches(".zip");
+        let mut groups: BTreeMap<String, Vec<Submission>> = BTreeMap::new();
+        for submission in submissions {
+            let extension = Path::new(&submission.attachment.filename)
+                .extension()
+                .and_then(std::ffi::OsStr::to_str)
+                .unwrap_or_default()
+                .to_lowercase();
+            groups.entry(extension).or_default().push(submission);
+        }
+        groups
+            .into_iter()
+            .map(|(extension, submissions)| ArchivePart {
+                zip_file_name: format!("{}-{}.zip", stem, extension),
+                label: Some(extension),
+                submissions,
+            })
+            .collect()
+    } else {
+        vec![ArchivePart {
+            label: None,
+            zip_file_name,
+            submissions,
+        }]
+    };
+
+    let mut upload_key = None;
+    if let [part] = parts.as_slice() {
+        let key = UploadKey {
+            user_id: ctx.author().id,
+            move_name: move_name.clone(),
+            contents: contents_hash(&part.submissions, &part.zip_file_name, &options),
+        };
+        let recent_upload = ctx.data().recent_uploads.lock().unwrap().get(&key);
+        if let Some(reply) = recent_upload {
+            reply::send_text(
+                ctx,
+                format!(
+                    "{}\nThese clips were just uploaded, reusing the link",
+                    reply
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+        upload_key = Some(key);
+    }
+
+    let split = parts.len() > 1;
+    let mut links = Vec::new();
+    let mut any_delivered = false;
+    for part in parts {
+        let (returned_dir, archive_result) = build_archive(
+            ctx,
+            &config,
+            dir,
+            part.zip_file_name.clone(),
+            &move_name,
+            part.submissions,
+            std::mem::take(&mut notes),
+            thread_info.clone(),
+            options.clone(),
+        )
+        .await?;
+        dir = returned_dir;
+
+        if !archive_result.notes.is_empty() {
+            let notes = archive_result.notes.join("\n");
+            let notes = match &part.label {
+                Some(label) => format!("`.{}` archive:\n{}", label, notes),
+                None => notes,
+            };
+            reply::send_text(ctx, notes).await?;
+        }
+
+        let delivered = if split {
+            upload_archive(
+                ctx,
+                move_name.clone(),
+                std::slice::from_ref(&move_name),
+                archive_result.archive,
+                &part.zip_file_name,
+                archive_result.clips,
+                None,
+            )
+            .await?
+        } else {
+            deliver(
+                ctx,
+                move_name.clone(),
+                std::slice::from_ref(&move_name),
+                archive_result.archive,
+                &part.zip_file_name,
+                archive_result.clips,
+                upload_key.take(),
+                public,
+            )
+            .await?
+        };
+        if let Some(delivered) = delivered {
+            any_delivered = true;
+            // a failed save only makes later pulls with `skip_archived` include these clips again
+            let recorded = ctx
+                .data()
+                .clip_registry
+                .lock()
+                .unwrap()
+                .record(&move_name, archive_result.attachment_ids);
+            if let Err(e) = recorded {
+                tracing::warn!(
+                    "Failed to record the archived clips of {}: {:#}",
+                    move_name,
+                    e
+                );
+            }
+            // an attached archive is already in a reply, there's no link to list
+            if let Delivered::Uploaded(uploaded) = delivered {
+                links.push(match part.label {
+                    Some(label) => format!("`.{}`: {}", label, uploaded.reply),
+                    None => uploaded.reply,
+                });
+            }
+        }
+    }
+
+    let delivered = (!links.is_empty()).then(|| links.join("\n"));
+    if split && let Some(reply) = &delivered {
+        if public {
+            reply::send_public_text(ctx, reply.clone()).await?;
+        } else {
+            reply::send_text(ctx, reply.clone()).await?;
+        }
+    }
+
+    if let Some(reply) = delivered.filter(|_| post_to_thread) {
+        let message = format!("{} pulled `{}`:\n{}", ctx.author().name, move_name, reply);
+        if let Err(e) = thread_id.say(ctx, message).await {
+            tracing::warn!("Failed to post the link in thread {}: {}", thread_id, e);
+            reply::send_text(
+                ctx,
+                "Couldn't post the link in this thread, I need permission to send messages in it",
+            )
+            .await?;
+        }
+    }
+
+    // a pull that got no link to the user must not make the next `since_last` skip its clips
+    if any_delivered {
+        let mut pull_markers = ctx.data().pull_markers.lock().unwrap();
+        for (marker_key, newest_message_id) in new_markers {
+            pull_markers.set(marker_key, newest_message_id);
+        }
+    }
+
+    if config.keep_temp {
+        let path = dir.keep();
+        tracing::info!("Kept temporary directory {}", path.display());
+    } else {
+        dir.close()
+            .context("Failed to close and remove temporary directory")?;
+    }
+
+    Ok(())
+}
+
+/// Largest archive attached to the reply when uploading it fails, Discord's limit for bots
+pub(super) const MAX_DIRECT_ATTACHMENT_BYTES: u64 = 10 * 1024 * 1024;
+/// Longest rate limit waited out before uploading, longer ones are reported to the user
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+/// Times a user can retry a failed upload of the same archive
+const MAX_UPLOAD_RETRIES: usize = 3;
+/// How long the retry button works, the archive is kept on disk until then
+const UPLOAD_RETRY_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Submissions that go in one archive, labeled when the pull is split into several
+struct ArchivePart {
+    label: Option<String>,
+    zip_file_name: String,
+    submissions: Vec<Submission>,
+}
+
+/// Downloads the submissions into an archive named `zip_file_name` in `dir` on a blocking thread,
+/// `dir` is removed if that fails
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn build_archive(
+    ctx: Context<'_>,
+    config: &Config,
+    dir: TempDir,
+    zip_file_name: String,
+    move_name: &str,
+    submissions: Vec<Submission>,
+    notes: Vec<String>,
+    thread_info: Option<String>,
+    options: ArchiveOptions,
+) -> Result<(TempDir, ArchiveResult), Error> {
+    let attachment_timeout = config.attachment_timeout;
+    let user_agent = config.download_user_agent.clone();
+
+    let move_name = move_name.to_owned();
+    let http = ctx.serenity_context().http.clone();
+    let runtime = tokio::runtime::Handle::current();
+    let built = archive::run_in_dir(dir, move |dir| {
+        let source = HttpSource::new(attachment_timeout, &user_agent)?.with_refresh(http, runtime);
+        archive::create_archive(
+            &source,
+            dir.join(zip_file_name),
+            &move_name,
+            submissions,
+            notes,
+            thread_info.as_deref(),
+            options,
+        )
+    })
+    .await?;
+
+    Ok(built)
+}
+
+/// Paragraph of `thread-info.txt` about `thread`
+fn describe_thread(thread: &GuildChannel, scan: &ThreadScan) -> String {
+    // threads from before Discord started recording it have no creation timestamp
+    let created_at = thread
+        .thread_metadata
+        .and_then(|metadata| metadata.create_timestamp)
+        .unwrap_or_else(|| thread.id.created_at());
+    format!(
+        "Thread: {}\nLink: https://discord.com/channels/{}/{}\nCreated: {}\nMessages scanned: {}\nParticipants: {}\n",
+        thread.name,
+        thread.guild_id,
+        thread.id,
+        created_at,
+        scan.messages_scanned,
+        scan.authors.iter().cloned().collect::<Vec<_>>().join(", ")
+    )
+}
+
+/// Scans `thread` for videos posted after `marker`, or all of them without one,
+/// adding them to `submissions` in `folder` until the attachment limit is reached
+async fn scan_thread(
+    ctx: Context<'_>,
+    thread: &GuildChannel,
+    marker: Option<MessageId>,
+    folder: Option<String>,
+    filter: &ScanFilter<'_>,
+    submissions: &mut Vec<Submission>,
+    notes: &mut Vec<String>,
+) -> Result<ThreadScan, Error> {
+    let mut newest_message_id: Option<MessageId> = None;
+    let mut already_archived = 0;
+    let mut from_bots = 0;
+    let mut messages_scanned = 0;
+    let mut authors = BTreeSet::new();
+
+    // the thread's id is never newer than its first message, forum posts share it with theirs
+    let after = marker.max(filter.posted_after);
+    let start = match after {
+        Some(after) => MessageId::new(after.get() + 1),
+        None => MessageId::new(thread.id.get()),
+    };
+    let mut pages = MessagePages::new(
+        ctx.serenity_context().http.clone(),
+        thread.id,
+        start,
+        thread.last_message_id,
+        ctx.data().config.load().scan_concurrency.get(),
+    );
+
+    while let Some(messages) = pages.next().await.map_err(BotError::Scan)? {
+        newest_message_id = newest_message_id.or(messages.first().map(|m| m.id));
+        for message in messages {
+            messages_scanned += 1;
+            authors.insert(message.author.name.clone());
+            for attachment in &message.attachments {
+                if !filter.keeps(&message, attachment) {
+                    continue;
+                }
+                if !filter.include_bots && message.author.bot {
+                    from_bots += 1;
+                    continue;
+                }
+                if filter.already_archived.contains(&attachment.id) {
+                    already_archived += 1;
+                    continue;
+                }
+                if let Some(max) = filter
+                    .max_attachments
+                    .filter(|&max| submissions.len() >= max)
+                {
+                    notes.push(format!(
+                        "Attachment limit {} reached. Messages from {} and earlier were not downloaded",
+                        max,
+                        attachment.id.created_at()
+                    ));
+                    return Ok(ThreadScan {
+                        newest_message_id,
+                        limit_reached: true,
+                        already_archived,
+                        from_bots,
+                        messages_scanned,
+                        authors,
+                    });
+                }
+                submissions.push(Submission {
+                    attachment: attachment.clone(),
+                    channel_id: thread.id,
+                    message_id: message.id,
+                    folder: folder.clone(),
+                    move_name: None,
+                    username: message.author.name.to_owned(),
+                    text: message.content.to_owned(),
+                });
+            }
+        }
+    }
+
+    Ok(ThreadScan {
+        newest_message_id,
+        limit_reached: false,
+        already_archived,
+        from_bots,
+        messages_scanned,
+        authors,
+    })
+}
+
+/// Uploads the archive and replies with its download link, returns how it reached the user if it did.
+/// `name` titles the reply, `moves` are the moves whose clips are in the archive
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn deliver(
+    ctx: Context<'_>,
+    name: String,
+    moves: &[String],
+    archive: PathBuf,
+    zip_file_name: &str,
+    clips: usize,
+    upload_key: Option<UploadKey>,
+    public: bool,
+) -> Result<Option<Delivered>, Error> {
+    let Some(delivered) = upload_archive(
+        ctx,
+        name.clone(),
+        moves,
+        archive,
+        zip_file_name,
+        clips,
+        upload_key,
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+    let Delivered::Uploaded(uploaded) = &delivered else {
+        return Ok(Some(delivered));
+    };
+
+    if ctx.data().config.load().embed_replies {
+        let mut embed = CreateEmbed::new()
+            .title(&name)
+            .field(
+                "Download",
+                format!("[{}]({})", zip_file_name, uploaded.link),
+                false,
+            )
+            .field("Size", archive::format_size(uploaded.size_bytes), true)
+            .field("Clips", uploaded.clips.to_string(), true)
+            .footer(CreateEmbedFooter::new(expiry_note(uploaded.expires_hours)));
+        if !uploaded.mirror_links.is_empty() {
+            embed = embed.field("Mirrors", uploaded.mirror_links.join("\n"), false);
+        }
+        reply::send_embed(ctx, embed, public).await?;
+    } else if public {
+        reply::send_public_text(ctx, uploaded.reply.clone()).await?;
+    } else {
+        reply::send_text(ctx, uploaded.reply.clone()).await?;
+    }
+
+    Ok(Some(delivered))
+}
+
+/// How an archive reached the user
+pub(super) enum Delivered {
+    Uploaded(Uploaded),
+    /// Uploading failed, so the archive was attached to a reply instead
+    Attached,
+}
+
+/// An archive that was uploaded
+pub(super) struct Uploaded {
+    /// Plain text reply with the links and when they expire
+    reply: String,
+    link: String,
+    mirror_links: Vec<String>,
+    size_bytes: u64,
+    clips: usize,
+    expires_hours: u64,
+}
+
+fn expiry_note(expires_hours: u64) -> String {
+    format!(
+        "Link expires in {} hour{}",
+        expires_hours,
+        if expires_hours == 1 { "" } else { "s" }
+    )
+}
+
+/// Uploads the archive and returns its download links, or attaches it to a reply if uploading fails
+/// and it's small enough. `None` after replying why it didn't reach the user
+async fn upload_archive(
+    ctx: Context<'_>,
+    name: String,
+    moves: &[String],
+    archive: PathBuf,
+    zip_file_name: &str,
+    clips: usize,
+    upload_key: Option<UploadKey>,
+) -> Result<Option<Delivered>, Error> {
+    let config = ctx.data().config.load_full();
+    let archive_size = std::fs::metadata(&archive)
+        .context("Failed to read archive size")
+        .map_err(BotError::Upload)?
+        .len();
+    // the last archive's when a pull is split
+    Span::current().record("archive_size", archive_size);
+
+    if let Some(command) = &config.archive_scan_command {
+        let verdict = antivirus::check(command, &archive)
+            .await
+            .context("Failed to scan archive")
+            .map_err(BotError::Upload)?;
+        if let Verdict::Flagged(report) = verdict {
+            Metrics::increment(&ctx.data().metrics.failures);
+            tracing::warn!(
+                "Archive of {} was flagged by the scanner:\n{}",
+                name,
+                report
+            );
+            reply::send_text(
+                ctx,
+                "The archive was flagged by the virus scanner and was not uploaded",
+            )
+            .await?;
+            return Ok(None);
+        }
+    }
+
+    // waiting out a short rate limit beats a failed upload the user has to retry
+    let rate_limit = ctx.data().uploader.rate_limit();
+    if let Some(limit) = rate_limit.as_ref().filter(|limit| limit.is_low()) {
+        tracing::warn!(
+            "Upload host rate limit is nearly used up, {} uploads left",
+            limit.remaining.unwrap_or_default()
+        );
+        reply::send_text(
+            ctx,
+            "The upload host is close to its rate limit, the next pulls may have to wait",
+        )
+        .await?;
+    }
+    if let Some(wait) = rate_limit.and_then(|limit| limit.wait()) {
+        if wait > MAX_RATE_LIMIT_WAIT {
+            let resets_at = SystemTime::now() + wait;
+            reply::send_text(
+                ctx,
+                format!(
+                    "The upload host isn't taking more uploads right now, try again <t:{}:R>",
+                    resets_at
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs()
+                ),
+            )
+            .await?;
+            return Ok(None);
+        }
+        tracing::info!(
+            "Upload host rate limit is used up, waiting {}s before uploading",
+            wait.as_secs()
+        );
+        tokio::time::sleep(wait).await;
+    }
+
+    let quota = config.guild_daily_quota_bytes.zip(ctx.guild_id());
+    if let Some((limit, guild_id)) = quota {
+        let reserved =
+            ctx.data()
+                .upload_quota
+                .lock()
+                .unwrap()
+                .try_reserve(guild_id, archive_size, limit);
+        if let Err(remaining) = reserved {
+            reply::send_text(
+                ctx,
+                format!(
+                    "This server's daily upload quota is used up, {} of {} left but the archive is {}. The quota resets <t:{}:R>",
+                    archive::format_size(remaining),
+                    archive::format_size(limit),
+                    archive::format_size(archive_size),
+                    UploadQuota::resets_at()
+                ),
+            )
+            .await?;
+            return Ok(None);
+        }
+    }
+    // failed uploads don't count against the quota
+    let release_quota = || {
+        if let Some((_, guild_id)) = quota {
+            ctx.data()
+                .upload_quota
+                .lock()
+                .unwrap()
+                .release(guild_id, archive_size);
+        }
+    };
+
+    // an archive of several moves expires with the shortest lived of them
+    let expires_hours = moves
+        .iter()
+        .map(|move_name| config.upload_expires_hours(move_name))
+        .min()
+        .unwrap_or(config.upload_expires_hours);
+    let mut retries = 0;
+    let response = loop {
+        let e = match ctx
+            .data()
+            .uploader
+            .upload(archive.clone(), expires_hours)
+            .await
+        {
+            Ok(response) => break response,
+            Err(e) => e,
+        };
+        Span::current().record("upload_success", false);
+        if e.is::<upload::TooLarge>() {
+            release_quota();
+            Metrics::increment(&ctx.data().metrics.failures);
+            reply::send_text(
+                ctx,
+                format!(
+                    "The archive is too large for the upload host ({}), try narrowing the pull with `since_last`, `user` or `extensions`",
+                    archive::format_size(archive_size)
+                ),
+            )
+            .await?;
+            return Ok(None);
+        }
+        tracing::warn!("Failed to upload the archive of {}: {:#}", name, e);
+
+        // the download and zip work is kept, small archives fit in a reply
+        if archive_size <= MAX_DIRECT_ATTACHMENT_BYTES {
+            release_quota();
+            let file = std::fs::read(&archive)
+                .context("Failed to read archive")
+                .map_err(BotError::Upload)?;
+            reply::send_file(
+                ctx,
+                "Uploading the archive failed, so here it is attached instead",
+                file,
+                zip_file_name,
+            )
+            .await?;
+            return Ok(Some(Delivered::Attached));
+        }
+        let retry = retries < MAX_UPLOAD_RETRIES
+            && reply::ask(
+                ctx,
+                "Uploading the archive failed, the clips are still downloaded so you can retry just the upload",
+                "Retry upload",
+                format!("{}-retry-upload-{}", ctx.id(), retries),
+                UPLOAD_RETRY_TIMEOUT,
+            )
+            .await?;
+        if !retry {
+            release_quota();
+            return Err(BotError::Upload(e));
+        }
+        retries += 1;
+    };
+
+    let link_reply = if let Some(url) = upload::parse_download_url(&response.body) {
+        let span = Span::current();
+        span.record("upload_success", true);
+        if let Some(host) = reqwest::Url::parse(&url)
+            .ok()
+            .as_ref()
+            .and_then(reqwest::Url::host_str)
+        {
+            span.record("link_host", host);
+        }
+        let metrics = &ctx.data().metrics;
+        Metrics::increment(&metrics.pulls_served);
+        metrics
+            .bytes_uploaded
+            .fetch_add(archive_size, Ordering::Relaxed);
+
+        let link = ctx.data().uploader.download_link(&url, zip_file_name);
+        if let Some(webhook_url) = &config.completion_webhook_url {
+            webhook::notify_completion(
+                webhook_url.clone(),
+                config.webhook_user_agent.clone(),
+                Completion {
+                    move_name: moves.join(", "),
+                    submitter: ctx.author().name.clone(),
+                    link: link.clone(),
+                    size_bytes: archive_size,
+                    clips,
+                },
+            );
+        }
+        ctx.data().history.lock().unwrap().push(UploadRecord {
+            moves: moves.to_vec(),
+            url,
+            link: link.clone(),
+            token: response.token,
+            archive: config.keep_temp.then_some(archive),
+            clips,
+            user_id: ctx.author().id,
+            guild_id: ctx.guild_id(),
+            uploaded_at: Timestamp::now(),
+            expires_hours,
+        });
+
+        let mirror_links: Vec<String> = response
+            .mirrors
+            .iter()
+            .map(|url| ctx.data().uploader.download_link(url, zip_file_name))
+            .collect();
+        let reply = format!(
+            "{}{}\n{}",
+            link,
+            mirror_links
+                .iter()
+                .map(|link| format!("\nMirror: {}", link))
+                .collect::<String>(),
+            expiry_note(expires_hours)
+        );
+        // remembered before replying, the reply is what fails when the interaction expired
+        if let Some(upload_key) = upload_key {
+            ctx.data().recent_uploads.lock().unwrap().insert(
+                upload_key,
+                link.clone(),
+                reply.clone(),
+            );
+        }
+        Some(Delivered::Uploaded(Uploaded {
+            reply,
+            link,
+            mirror_links,
+            size_bytes: archive_size,
+            clips,
+            expires_hours,
+        }))
+    } else {
+        Span::current().record("upload_success", false);
+        release_quota();
+        Metrics::increment(&ctx.data().metrics.failures);
+        tracing::error!(
+            "Failed to create download link. Response:\n{}",
+            response.body
+        );
+        reply::send_text(ctx, "Failed to create download link").await?;
+        None
+    };
+
+    Ok(link_reply)
+}
@@ -0,0 +1,319 @@
+use crate::archive::{ArchiveOptions, Submission, clip_count};
+use crate::commands::pull;
+use crate::config::MAX_TOTAL_SIZE_BYTES;
+use crate::data::{ActivePull, Context, Error};
+use crate::moves;
+use crate::reply;
+use anyhow::Context as AnyhowContext;
+use poise::serenity_prelude::AttachmentId;
+use std::collections::HashMap;
+use tracing::{Span, instrument};
+
+/// Most moves one pull may list, each of them is a thread to scan
+const MAX_MOVES: usize = 25;
+
+#[poise::command(slash_command, guild_only)]
+#[instrument(name = "pull_many", skip_all, fields(
+    id = ctx.id(),
+    username = ctx.author().name,
+    moves = moves,
+    submissions = tracing::field::Empty,
+    archive_size = tracing::field::Empty,
+    upload_success = tracing::field::Empty,
+    link_host = tracing::field::Empty,
+))]
+pub async fn pull_many(
+    ctx: Context<'_>,
+    #[description = "Move names, separated by commas"] moves: String,
+    #[description = "Make an archive per move instead of one with a folder per move"]
+    separate: Option<bool>,
+    #[description = "Post the links so everyone in the channel can see them"] public: Option<bool>,
+) -> Result<(), Error> {
+    let public = public.unwrap_or(false);
+    if public {
+        ctx.defer().await
+    } else {
+        ctx.defer_ephemeral().await
+    }
+    .context("Failed to defer response")?;
+    let _active_pull = ActivePull::new(&ctx.data().metrics.active_pulls);
+
+    let move_list = ctx.data().move_list.load_full();
+    if move_list.is_empty() {
+        reply::send_text(ctx, moves::MOVE_LIST_NOT_CONFIGURED).await?;
+        return Ok(());
+    }
+
+    let names: Vec<&str> = moves
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect();
+    if names.is_empty() {
+        reply::send_text(ctx, "List the moves to pull, separated by commas").await?;
+        return Ok(());
+    }
+
+    let mut move_names: Vec<String> = Vec::new();
+    let mut unknown = Vec::new();
+    for name in names {
+        match moves::resolve(&move_list, name) {
+            Some(move_name) if move_names.iter().any(|known| known == move_name) => {}
+            Some(move_name) => move_names.push(move_name.to_owned()),
+            None => unknown.push(format!("`{}`", name)),
+        }
+    }
+    if !unknown.is_empty() {
+        reply::send_text(
+            ctx,
+            format!(
+                "Moves not found: {}. Use `/search <page_number>` to get the move names",
+                unknown.join(", ")
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+    if move_names.len() > MAX_MOVES {
+        reply::send_text(ctx, format!("Pull at most {} moves at once", MAX_MOVES)).await?;
+        return Ok(());
+    }
+
+    let config = ctx.data().config.load_full();
+    let max_file_size = config
+        .max_file_size_bytes
+        .map_or(MAX_TOTAL_SIZE_BYTES, |max| max.min(MAX_TOTAL_SIZE_BYTES));
+
+    // the size limit covers every move together, later moves are left out once it's reached
+    let mut groups: Vec<(String, Vec<Submission>)> = Vec::new();
+    let mut counts = Vec::new();
+    let mut notes = Vec::new();
+    let mut total_size = 0;
+    let mut size_limit_reached = false;
+    for move_name in move_names {
+        if size_limit_reached {
+            counts.push(format!(
+                "`{}`: left out, size limit 512MB reached",
+                move_name
+            ));
+            continue;
+        }
+        let Some(thread_id) = ctx.data().move_thread(&move_name) else {
+            counts.push(format!(
+                "`{}`: thread not known, run `/pull` in it once",
+                move_name
+            ));
+            continue;
+        };
+        let thread = thread_id
+            .to_channel(ctx)
+            .await
+            .ok()
+            .and_then(|channel| channel.guild())
+            .filter(|thread| Some(thread.guild_id) == ctx.guild_id());
+        let Some(thread) = thread else {
+            counts.push(format!(
+                "`{}`: thread <#{}> can't be accessed",
+                move_name, thread_id
+            ));
+            continue;
+        };
+
+        let (clips, thread_notes) = pull::thread_clips(ctx, &config, &thread).await?;
+        notes.extend(
+            thread_notes
+                .into_iter()
+                .map(|note| format!("`{}`: {}", move_name, note)),
+        );
+
+        let mut kept = Vec::with_capacity(clips.len());
+        for clip in clips {
+            // clips too large for any archive are left out by the archive itself
+            let size = u64::from(clip.attachment.size);
+            if size <= max_file_size {
+                if total_size + size > MAX_TOTAL_SIZE_BYTES {
+                    size_limit_reached = true;
+                    notes.push(format!(
+                        "Size limit 512MB reached, older clips of `{}` and the moves after it were left out",
+                        move_name
+                    ));
+                    break;
+                }
+                total_size += size;
+            }
+            kept.push(clip);
+        }
+
+        if kept.is_empty() {
+            counts.push(format!("`{}`: no videos", move_name));
+        } else {
+            counts.push(format!("`{}`: {}", move_name, clip_count(kept.len())));
+            groups.push((move_name, kept));
+        }
+    }
+
+    if groups.is_empty() {
+        reply::send_text(
+            ctx,
+            format!("No video (.mov or .mp4) found:\n{}", counts.join("\n")),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    Span::current().record(
+        "submissions",
+        groups.iter().map(|(_, clips)| clips.len()).sum::<usize>(),
+    );
+
+    let mut summary = format!(
+        "Pulling {} of {} moves:\n{}",
+        groups.len(),
+        counts.len(),
+        counts.join("\n")
+    );
+    for note in &notes {
+        summary.push('\n');
+        summary.push_str(note);
+    }
+    reply::send_text_or_file(ctx, summary, "pull-many.txt").await?;
+
+    let options = ArchiveOptions {
+        include_text: false,
+        thumbnails: config.thumbnails,
+        max_file_size: config.max_file_size_bytes,
+        oldest_first: false,
+        numbered: false,
+        preserve_names: false,
+        compression: config.archive_compression,
+        stored_extensions: config.stored_extensions.clone(),
+        transcode: None,
+        duration: None,
+        download_concurrency: config.download_concurrency,
+        download_delay: config.download_delay,
+    };
+
+    let mut dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    if separate.unwrap_or(false) {
+        for (move_name, submissions) in groups {
+            let zip_file_name = format!("{}.zip", move_name);
+            let (returned_dir, archive_result) = pull::build_archive(
+                ctx,
+                &config,
+                dir,
+                zip_file_name.clone(),
+                &move_name,
+                submissions,
+                Vec::new(),
+                None,
+                options.clone(),
+            )
+            .await?;
+            dir = returned_dir;
+            if !archive_result.notes.is_empty() {
+                reply::send_text(
+                    ctx,
+                    format!("`{}`:\n{}", move_name, archive_result.notes.join("\n")),
+                )
+                .await?;
+            }
+
+            let delivered = pull::deliver(
+                ctx,
+                move_name.clone(),
+                std::slice::from_ref(&move_name),
+                archive_result.archive,
+                &zip_file_name,
+                archive_result.clips,
+                None,
+                public,
+            )
+            .await?;
+            if delivered.is_some() {
+                record_archived(ctx, &move_name, archive_result.attachment_ids);
+            }
+        }
+    } else {
+        let name = format!("{} moves", groups.len());
+        let zip_file_name = format!("{}-moves.zip", groups.len());
+        let move_names: Vec<String> = groups.iter().map(|(name, _)| name.clone()).collect();
+        let mut moves_of: HashMap<AttachmentId, String> = HashMap::new();
+        let mut submissions = Vec::new();
+        for (move_name, clips) in groups {
+            for mut clip in clips {
+                moves_of.insert(clip.attachment.id, move_name.clone());
+                // named after its own move, the archive's name only labels the zip and the reply
+                clip.folder = Some(move_name.clone());
+                clip.move_name = Some(move_name.clone());
+                submissions.push(clip);
+            }
+        }
+
+        let (returned_dir, archive_result) = pull::build_archive(
+            ctx,
+            &config,
+            dir,
+            zip_file_name.clone(),
+            &name,
+            submissions,
+            Vec::new(),
+            None,
+            options,
+        )
+        .await?;
+        dir = returned_dir;
+        if !archive_result.notes.is_empty() {
+            reply::send_text(ctx, archive_result.notes.join("\n")).await?;
+        }
+
+        let delivered = pull::deliver(
+            ctx,
+            name,
+            &move_names,
+            archive_result.archive,
+            &zip_file_name,
+            archive_result.clips,
+            None,
+            public,
+        )
+        .await?;
+        if delivered.is_some() {
+            let mut archived: HashMap<String, Vec<AttachmentId>> = HashMap::new();
+            for attachment_id in archive_result.attachment_ids {
+                if let Some(move_name) = moves_of.remove(&attachment_id) {
+                    archived.entry(move_name).or_default().push(attachment_id);
+                }
+            }
+            for (move_name, attachment_ids) in archived {
+                record_archived(ctx, &move_name, attachment_ids);
+            }
+        }
+    }
+
+    if config.keep_temp {
+        let path = dir.keep();
+        tracing::info!("Kept temporary directory {}", path.display());
+    } else {
+        dir.close()
+            .context("Failed to close and remove temporary directory")?;
+    }
+
+    Ok(())
+}
+
+/// Adds the clips to the clip registry, a failed save only makes `skip_archived` include them again
+fn record_archived(ctx: Context<'_>, move_name: &str, attachment_ids: Vec<AttachmentId>) {
+    let recorded = ctx
+        .data()
+        .clip_registry
+        .lock()
+        .unwrap()
+        .record(move_name, attachment_ids);
+    if let Err(e) = recorded {
+        tracing::warn!(
+            "Failed to record the archived clips of {}: {:#}",
+            move_name,
+            e
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use crate::config::{Config, RESTART_REQUIRED};
+use crate::data::{Context, Error};
+use crate::reply;
+use std::sync::Arc;
+use tracing::instrument;
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    required_permissions = "ADMINISTRATOR"
+)]
+#[instrument(name = "reload_config", skip_all, fields(id = ctx.id(), username = ctx.author().name))]
+pub async fn reload_config(ctx: Context<'_>) -> Result<(), Error> {
+    // values from .env replace the ones loaded at startup, it's fine if there is none
+    dotenvy::dotenv_override().ok();
+
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            reply::send_text(
+                ctx,
+                format!("Invalid configuration, keeping the current one: {:#}", e),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let current = ctx.data().config.load_full();
+    let changed = current.changed(&config);
+    ctx.data().config.store(Arc::new(config));
+    tracing::info!("Reloaded the configuration, changed: {:?}", changed);
+
+    let reply = if changed.is_empty() {
+        "Reloaded the configuration, nothing changed".to_owned()
+    } else {
+        let restart: Vec<&str> = changed
+            .iter()
+            .copied()
+            .filter(|name| RESTART_REQUIRED.contains(name))
+            .collect();
+        let mut reply = format!(
+            "Reloaded the configuration, changed: {}",
+            changed.join(", ")
+        );
+        if !restart.is_empty() {
+            reply.push_str(&format!(
+                "\nThese take effect after a restart: {}",
+                restart.join(", ")
+            ));
+        }
+        reply
+    };
+    reply::send_text(ctx, reply).await?;
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use crate::data::{Context, Error};
+use crate::reply;
+use poise::serenity_prelude as serenity;
+use tracing::instrument;
+
+#[poise::command(slash_command)]
+#[instrument(name = "report", skip_all, fields(id = ctx.id(), username = ctx.author().name))]
+pub async fn report(
+    ctx: Context<'_>,
+    #[description = "What went wrong"]
+    #[max_length = 1000]
+    message: String,
+) -> Result<(), Error> {
+    let last_command = ctx
+        .data()
+        .last_commands
+        .lock()
+        .unwrap()
+        .get(&ctx.author().id)
+        .cloned()
+        .unwrap_or_else(|| "none".to_owned());
+
+    tracing::warn!(
+        "Report from {} in channel {}, last command: {}\n{}",
+        ctx.author().name,
+        ctx.channel_id(),
+        last_command,
+        message
+    );
+
+    let config = ctx.data().config.load_full();
+    if let Some(url) = &config.report_webhook_url {
+        let content = format!(
+            "Report from {} in <#{}>\nLast command: `{}`\n>>> {}",
+            ctx.author().name,
+            ctx.channel_id(),
+            // a backtick would close the code span early, backslashes don't escape inside it
+            last_command.replace('`', "'"),
+            message
+        );
+        let forwarded = match serenity::Webhook::from_url(&ctx, url).await {
+            Ok(webhook) => webhook
+                .execute(
+                    &ctx,
+                    false,
+                    // reports are user written, `@everyone` in one must not ping the channel
+                    serenity::ExecuteWebhook::new()
+                        .content(content)
+                        .allowed_mentions(serenity::CreateAllowedMentions::new()),
+                )
+                .await
+                .map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = forwarded {
+            tracing::error!("Failed to forward report to webhook: {}", e);
+        }
+    }
+
+    reply::send_text(
+        ctx,
+        "Thanks for the report, the maintainers will take a look",
+    )
+    .await?;
+
+    Ok(())
+}
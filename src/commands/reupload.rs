@@ -0,0 +1,99 @@
+use crate::commands::pull::{self, PullOptions, SortOrder};
+use crate::data::{Context, Error};
+use crate::moves;
+use crate::reply;
+use anyhow::Context as AnyhowContext;
+use tracing::instrument;
+
+#[poise::command(slash_command)]
+#[instrument(name = "reupload", skip_all, fields(
+    id = ctx.id(),
+    username = ctx.author().name,
+    move_name = move_name,
+    submissions = tracing::field::Empty,
+    archive_size = tracing::field::Empty,
+    upload_success = tracing::field::Empty,
+    link_host = tracing::field::Empty,
+))]
+pub async fn reupload(
+    ctx: Context<'_>,
+    #[description = "Move name"] move_name: String,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral()
+        .await
+        .context("Failed to defer response")?;
+
+    let move_name = moves::resolve(&ctx.data().move_list.load(), &move_name)
+        .map(str::to_owned)
+        .unwrap_or(move_name);
+
+    let archive = ctx
+        .data()
+        .history
+        .lock()
+        .unwrap()
+        .latest_archive(&move_name, ctx.guild_id(), ctx.author().id)
+        .and_then(|record| Some((record.archive.clone()?, record.clips, record.moves.clone())));
+
+    match archive {
+        Some((archive, clips, moves)) => {
+            // named as it was pulled, with `archive_name` or the extension of a split pull
+            let zip_file_name = archive.file_name().map_or_else(
+                || format!("{}.zip", move_name),
+                |name| name.to_string_lossy().into_owned(),
+            );
+            pull::deliver(
+                ctx,
+                move_name,
+                &moves,
+                archive,
+                &zip_file_name,
+                clips,
+                None,
+                false,
+            )
+            .await?;
+            Ok(())
+        }
+        None => {
+            reply::send_text(
+                ctx,
+                "The last archive of this move is no longer available, pulling it again",
+            )
+            .await?;
+
+            pull::run(
+                ctx,
+                PullOptions {
+                    move_name,
+                    include_text: false,
+                    since_last: false,
+                    extensions: Vec::new(),
+                    sort: SortOrder::Newest,
+                    numbered: false,
+                    archive_name: None,
+                    compression: None,
+                    user: None,
+                    exclude_user: None,
+                    public: false,
+                    tag: None,
+                    post_to_thread: false,
+                    transcode: false,
+                    min_seconds: None,
+                    max_seconds: None,
+                    split_by_extension: false,
+                    skip_archived: false,
+                    reaction: None,
+                    min_reactions: None,
+                    thread_info: false,
+                    within: None,
+                    include_bots: None,
+                    playlist: None,
+                    largest_per_user: false,
+                    preserve_names: false,
+                },
+            )
+            .await
+        }
+    }
+}
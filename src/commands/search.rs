@@ -0,0 +1,305 @@
+use crate::data::{Context, Error, Metrics};
+use crate::moves;
+use crate::reply;
+use poise::serenity_prelude::CreateEmbed;
+use std::collections::HashMap;
+use tracing::instrument;
+
+const MIN_SEARCH_TERM_LENGTH: usize = 2;
+const MAX_SEARCH_TERM_LENGTH: usize = 100;
+/// Longest embed description Discord accepts, longer results are sent as text
+const MAX_EMBED_DESCRIPTION_LENGTH: usize = 4096;
+/// Longest embed title Discord accepts, many short terms make a longer heading
+const MAX_EMBED_TITLE_LENGTH: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter)]
+pub enum MatchMode {
+    #[name = "any"]
+    Any,
+    #[name = "all"]
+    All,
+}
+
+impl MatchMode {
+    pub(super) fn matches(self, line: &str, terms: &[&str]) -> bool {
+        match self {
+            MatchMode::Any => terms.iter().any(|term| line.contains(term)),
+            MatchMode::All => terms.iter().all(|term| line.contains(term)),
+        }
+    }
+}
+
+/// How closely a term matches a move, closer matches are listed first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum MatchRank {
+    /// The move starts with the term
+    Prefix,
+    /// The term starts a word of the move, after a `-`, `_` or space
+    WordStart,
+    /// The term is anywhere else in the move
+    Substring,
+}
+
+impl MatchRank {
+    /// Closest rank of any of `terms` in `line`
+    fn of(line: &str, terms: &[&str]) -> Option<Self> {
+        terms
+            .iter()
+            .flat_map(|term| line.match_indices(term))
+            .map(|(index, _)| {
+                if index == 0 {
+                    MatchRank::Prefix
+                } else if line[..index]
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| !c.is_alphanumeric())
+                {
+                    MatchRank::WordStart
+                } else {
+                    MatchRank::Substring
+                }
+            })
+            .min()
+    }
+}
+
+#[poise::command(slash_command)]
+#[instrument(name = "search", skip_all, fields(id = ctx.id(), username = ctx.author().name, search_term = search_term))]
+pub async fn search(
+    ctx: Context<'_>,
+    #[description = "Search terms, separated by spaces or commas"] search_term: String,
+    #[description = "Match moves containing any (default) or all of the terms"]
+    #[rename = "match"]
+    match_mode: Option<MatchMode>,
+) -> Result<(), Error> {
+    let search_term = search_term.trim().to_lowercase();
+    let match_mode = match_mode.unwrap_or(MatchMode::Any);
+
+    let terms = split_terms(&search_term);
+
+    if search_term.chars().count() > MAX_SEARCH_TERM_LENGTH
+        || terms.is_empty()
+        || terms
+            .iter()
+            .any(|term| term.chars().count() < MIN_SEARCH_TERM_LENGTH)
+    {
+        reply::send_text(
+            ctx,
+            format!(
+                "Search terms must be at least {} characters and {} characters in total, e.g. `/search 107`",
+                MIN_SEARCH_TERM_LENGTH, MAX_SEARCH_TERM_LENGTH
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let move_list = ctx.data().move_list.load_full();
+    if move_list.is_empty() {
+        reply::send_text(ctx, moves::MOVE_LIST_NOT_CONFIGURED).await?;
+        return Ok(());
+    }
+
+    Metrics::increment(&ctx.data().metrics.searches);
+
+    let matches: Vec<_> = ranked_matches(&move_list, &ctx.data().move_aliases, &terms, match_mode)
+        .into_iter()
+        .map(|(_, move_name, alias)| (move_name, alias))
+        .collect();
+
+    let searched = match terms.as_slice() {
+        [term] => format!("\"{}\"", term),
+        terms => {
+            let separator = match match_mode {
+                MatchMode::Any => " or ",
+                MatchMode::All => " and ",
+            };
+            terms
+                .iter()
+                .map(|term| format!("\"{}\"", term))
+                .collect::<Vec<_>>()
+                .join(separator)
+        }
+    };
+    if matches.is_empty() {
+        let searched = match terms.as_slice() {
+            [term] => term.to_string(),
+            _ => searched,
+        };
+        reply::send_text(ctx, format!("No move contains {}", searched)).await?;
+        return Ok(());
+    }
+    let heading = format!("Moves containing {}", searched);
+
+    // moves whose thread is known link to it
+    if let Some(guild_id) = ctx.guild_id() {
+        let threads: Vec<_> = matches
+            .iter()
+            .map(|(move_name, _)| ctx.data().move_thread(move_name))
+            .collect();
+        if threads.iter().any(Option::is_some) {
+            let description = matches
+                .iter()
+                .zip(threads)
+                .map(|(&(move_name, alias), thread_id)| {
+                    let name = match thread_id {
+                        Some(thread_id) => format!(
+                            "[{}](https://discord.com/channels/{}/{})",
+                            move_name, guild_id, thread_id
+                        ),
+                        None => move_name.to_owned(),
+                    };
+                    describe_match(name, move_name, alias)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            if description.chars().count() <= MAX_EMBED_DESCRIPTION_LENGTH {
+                let embed = CreateEmbed::new()
+                    .title(truncate(&heading, MAX_EMBED_TITLE_LENGTH))
+                    .description(description);
+                reply::send_embed(ctx, embed, false).await?;
+                return Ok(());
+            }
+        }
+    }
+
+    let results: Vec<String> = matches
+        .iter()
+        .map(|&(move_name, alias)| describe_match(move_name.to_owned(), move_name, alias))
+        .collect();
+    let reply = format!("{}:\n{}", heading, results.join("\n"));
+    reply::send_text_or_file(ctx, reply, "search-results.txt").await?;
+
+    Ok(())
+}
+
+/// Result line of a move shown as `name`, followed by its category and the alias it was found by
+fn describe_match(name: String, move_name: &str, alias: Option<&str>) -> String {
+    let mut result = match moves::move_category(move_name) {
+        Some(category) => format!("{} ({})", name, category),
+        None => name,
+    };
+    if let Some(alias) = alias {
+        result.push_str(&format!(" (alias: {})", alias));
+    }
+    result
+}
+
+/// `text` cut to at most `max` characters, ending in `…` if it was cut
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_owned();
+    }
+    let mut truncated: String = text.chars().take(max - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Search terms separated by whitespace or commas
+pub(super) fn split_terms(search_term: &str) -> Vec<&str> {
+    search_term
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|term| !term.is_empty())
+        .collect()
+}
+
+/// Moves matching `terms` with their rank, and the alias they were found by if their name
+/// doesn't match. Prefix matches come first, then word start matches, then the rest,
+/// each in move list order
+pub(super) fn ranked_matches<'a>(
+    move_list: &'a [String],
+    aliases: &'a HashMap<String, Vec<String>>,
+    terms: &[&str],
+    match_mode: MatchMode,
+) -> Vec<(MatchRank, &'a str, Option<&'a str>)> {
+    let mut matches: Vec<(MatchRank, &str, Option<&str>)> = move_list
+        .iter()
+        .filter_map(|move_name| {
+            // each move is listed once, by the alias only if its name doesn't match
+            if match_mode.matches(move_name, terms) {
+                let rank = MatchRank::of(move_name, terms)?;
+                return Some((rank, move_name.as_str(), None));
+            }
+            let alias = aliases
+                .get(move_name)?
+                .iter()
+                .find(|alias| match_mode.matches(alias, terms))?;
+            let rank = MatchRank::of(alias, terms)?;
+            Some((rank, move_name.as_str(), Some(alias.as_str())))
+        })
+        .collect();
+
+    // stable, so moves of the same rank keep their order
+    matches.sort_by_key(|(rank, _, _)| *rank);
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn move_list(moves: &[&str]) -> Vec<String> {
+        moves.iter().map(|&m| m.to_owned()).collect()
+    }
+
+    #[test]
+    fn rank_prefers_prefix_then_word_start() {
+        assert_eq!(
+            MatchRank::of("deal-second", &["deal"]),
+            Some(MatchRank::Prefix)
+        );
+        assert_eq!(
+            MatchRank::of("second_deal", &["deal"]),
+            Some(MatchRank::WordStart)
+        );
+        assert_eq!(
+            MatchRank::of("redeal", &["deal"]),
+            Some(MatchRank::Substring)
+        );
+        assert_eq!(
+            MatchRank::of("redeal-deal", &["deal"]),
+            Some(MatchRank::WordStart)
+        );
+        assert_eq!(
+            MatchRank::of("redeal", &["red", "deal"]),
+            Some(MatchRank::Prefix)
+        );
+        assert_eq!(MatchRank::of("shuffle", &["deal"]), None);
+    }
+
+    #[test]
+    fn matches_are_ordered_by_rank_then_move_list() {
+        let moves = move_list(&[
+            "08-bottoms-0401-redealing",
+            "08-bottoms-0409-deal_second",
+            "deal_bottom",
+            "08-bottoms-0410-second_deal",
+            "02-false_shuffles-0107-conleys_three_riffle_variation",
+        ]);
+        let aliases = HashMap::new();
+        let matches = ranked_matches(&moves, &aliases, &["deal"], MatchMode::Any);
+        assert_eq!(
+            matches,
+            vec![
+                (MatchRank::Prefix, "deal_bottom", None),
+                (MatchRank::WordStart, "08-bottoms-0409-deal_second", None),
+                (MatchRank::WordStart, "08-bottoms-0410-second_deal", None),
+                (MatchRank::Substring, "08-bottoms-0401-redealing", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn alias_matches_are_ranked_by_the_alias() {
+        let moves = move_list(&["04-lifts-0301-midl", "04-lifts-0302-tilt"]);
+        let aliases = HashMap::from([("04-lifts-0302-tilt".to_owned(), vec!["dl".to_owned()])]);
+        let matches = ranked_matches(&moves, &aliases, &["dl"], MatchMode::Any);
+        assert_eq!(
+            matches,
+            vec![
+                (MatchRank::Prefix, "04-lifts-0302-tilt", Some("dl")),
+                (MatchRank::Substring, "04-lifts-0301-midl", None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use crate::commands::search::{MatchMode, ranked_matches, split_terms};
+use crate::data::{Context, Error};
+use crate::moves;
+use crate::reply;
+use poise::ChoiceParameter;
+use tracing::instrument;
+
+/// Closest moves by edit distance listed in the reply
+const SCORED_MATCHES: usize = 20;
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    required_permissions = "ADMINISTRATOR"
+)]
+#[instrument(name = "search_debug", skip_all, fields(id = ctx.id(), username = ctx.author().name, search_term = search_term))]
+pub async fn search_debug(
+    ctx: Context<'_>,
+    #[description = "Search terms, as given to /search"] search_term: String,
+    #[description = "Match moves containing any (default) or all of the terms"]
+    #[rename = "match"]
+    match_mode: Option<MatchMode>,
+) -> Result<(), Error> {
+    let move_list = ctx.data().move_list.load_full();
+    if move_list.is_empty() {
+        reply::send_text(ctx, moves::MOVE_LIST_NOT_CONFIGURED).await?;
+        return Ok(());
+    }
+
+    let search_term = search_term.trim().to_lowercase();
+    let match_mode = match_mode.unwrap_or(MatchMode::Any);
+    let terms = split_terms(&search_term);
+
+    // the same matches in the same order as /search lists them
+    let matches = ranked_matches(&move_list, &ctx.data().move_aliases, &terms, match_mode);
+
+    let mut reply = format!(
+        "```\nterms: {:?}\nmode: {}\n\nmatches as /search ranks them ({}):\n",
+        terms,
+        match_mode.name(),
+        matches.len()
+    );
+    for (rank, move_name, alias) in &matches {
+        let rank = format!("{:?}", rank);
+        match alias {
+            Some(alias) => reply.push_str(&format!(
+                "  {:<9}  {} (alias: {})\n",
+                rank, move_name, alias
+            )),
+            None => reply.push_str(&format!("  {:<9}  {}\n", rank, move_name)),
+        }
+    }
+    // what /pull suggests when a move name isn't found
+    reply.push_str(&format!(
+        "\nclosest {} by edit distance to name or title:\n",
+        SCORED_MATCHES
+    ));
+    for (distance, move_name) in moves::scored_matches(&move_list, &search_term)
+        .into_iter()
+        .take(SCORED_MATCHES)
+    {
+        reply.push_str(&format!("  {:>4}  {}\n", distance, move_name));
+    }
+    reply.push_str("```");
+
+    reply::send_text_or_file(ctx, reply, "search-debug.txt").await?;
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use crate::data::{Context, Error};
+use crate::reply;
+use crate::upload;
+use anyhow::Context as AnyhowContext;
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+use tracing::instrument;
+
+const SELFTEST_FILE_NAME: &str = "gsoh-selftest.zip";
+/// The archive is only there to prove the upload works, so it expires quickly
+const SELFTEST_EXPIRES_HOURS: u64 = 1;
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    required_permissions = "ADMINISTRATOR"
+)]
+#[instrument(name = "selftest", skip_all, fields(id = ctx.id(), username = ctx.author().name))]
+pub async fn selftest(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral()
+        .await
+        .context("Failed to defer response")?;
+
+    let started_at = Instant::now();
+    let dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let archive = dir.path().join(SELFTEST_FILE_NAME);
+
+    let zip_path = archive.clone();
+    let zipped = tokio::task::spawn_blocking(move || write_dummy_archive(&zip_path))
+        .await
+        .context("Zip task failed")
+        .and_then(|result| result);
+    if let Err(e) = zipped {
+        reply::send_text(
+            ctx,
+            format!("Self-test failed to create the archive: {:#}", e),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let reply = match ctx
+        .data()
+        .uploader
+        .upload(archive, SELFTEST_EXPIRES_HOURS)
+        .await
+    {
+        Ok(response) => match upload::parse_download_url(&response.body) {
+            Some(url) => format!(
+                "Self-test passed in {:.1}s, the archive was created and uploaded:\n{}\nLink expires in {} hour",
+                started_at.elapsed().as_secs_f64(),
+                ctx.data().uploader.download_link(&url, SELFTEST_FILE_NAME),
+                SELFTEST_EXPIRES_HOURS
+            ),
+            None => format!(
+                "Self-test failed, the upload host didn't return a download link:\n{}",
+                response.body.trim()
+            ),
+        },
+        Err(e) => format!("Self-test failed to upload the archive: {:#}", e),
+    };
+
+    reply::send_text(ctx, reply).await?;
+
+    dir.close()
+        .context("Failed to close and remove temporary directory")?;
+
+    Ok(())
+}
+
+/// Zip with a single small text file at `path`
+fn write_dummy_archive(path: &Path) -> anyhow::Result<()> {
+    let file = std::fs::File::create(path).context("Failed to create archive")?;
+    let mut zip = zip::ZipWriter::new(file);
+    zip.start_file("selftest.txt", zip::write::SimpleFileOptions::default())
+        .context("Failed to start writing dummy file")?;
+    zip.write_all(b"GSOH bot self-test, safe to delete\n")
+        .context("Failed to write dummy file")?;
+    zip.finish().context("Failed to finish writing archive")?;
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use crate::data::{Context, Error};
+use crate::reply;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tracing::instrument;
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!(
+        "{}d {}h {}m {}s",
+        secs / 86400,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    required_permissions = "ADMINISTRATOR"
+)]
+#[instrument(name = "status", skip_all, fields(id = ctx.id(), username = ctx.author().name))]
+pub async fn status(ctx: Context<'_>) -> Result<(), Error> {
+    let data = ctx.data();
+
+    let reply = format!(
+        "Uptime: {}\nPulls served: {}\nActive pulls: {}\nMoves in move list: {}",
+        format_duration(data.started_at.elapsed()),
+        data.metrics.pulls_served.load(Ordering::Relaxed),
+        data.metrics.active_pulls.load(Ordering::Relaxed),
+        data.move_list.load().len()
+    );
+
+    reply::send_text(ctx, reply).await?;
+
+    Ok(())
+}
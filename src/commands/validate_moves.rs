@@ -0,0 +1,41 @@
+use crate::data::{Context, Error};
+use crate::moves;
+use crate::reply;
+use tracing::instrument;
+
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    required_permissions = "ADMINISTRATOR"
+)]
+#[instrument(name = "validate_moves", skip_all, fields(id = ctx.id(), username = ctx.author().name))]
+pub async fn validate_moves(ctx: Context<'_>) -> Result<(), Error> {
+    let config = ctx.data().config.load_full();
+    let path = &config.move_list_path;
+
+    let reply = match tokio::fs::read_to_string(path).await {
+        Ok(source) => {
+            let issues = moves::validate(&source);
+            if issues.is_empty() {
+                format!(
+                    "No problems found in {} ({} moves)",
+                    path.display(),
+                    source.lines().count()
+                )
+            } else {
+                format!(
+                    "Found {} problems in {}:\n{}",
+                    issues.len(),
+                    path.display(),
+                    issues.join("\n")
+                )
+            }
+        }
+        Err(e) => format!("Failed to read {}: {}", path.display(), e),
+    };
+
+    reply::send_text_or_file(ctx, reply, "move-list-problems.txt").await?;
+
+    Ok(())
+}
@@ -0,0 +1,702 @@
+use crate::archive::Compression;
+use crate::moves::MoveListSource;
+use crate::transcode::TranscodeSettings;
+use anyhow::Context as AnyhowContext;
+use poise::serenity_prelude::{Attachment, ChannelId};
+use std::collections::HashMap;
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use validator::ValidateUrl;
+
+const DEFAULT_ALLOWED_CONTENT_TYPES: [&str; 2] = ["video/quicktime", "video/mp4"];
+const DEFAULT_ALLOWED_EXTENSIONS: [&str; 2] = ["mov", "mp4"];
+/// Already compressed video and thumbnail formats
+const DEFAULT_STORED_EXTENSIONS: [&str; 5] = ["mov", "mp4", "webm", "mkv", "jpg"];
+pub const MAX_TOTAL_SIZE_BYTES: u64 = 512 * 1024 * 1024; // 512MB
+const DEFAULT_USER_AGENT: &str = "GsohDiscordBot/1.0 (https://github.com/tufourn/gsoh-discord-bot)";
+
+const DEFAULT_ATTACHMENT_TIMEOUT_SECONDS: u64 = 60;
+const DEFAULT_UPLOAD_URL: &str = "https://0x0.st";
+const DEFAULT_UPLOAD_EXPIRES_HOURS: u64 = 1;
+const DEFAULT_MOVE_LIST_PATH: &str = "move-list.txt";
+const DEFAULT_CLIP_REGISTRY_PATH: &str = "clip-registry.json";
+const DEFAULT_DRY_UPLOAD_DIR: &str = "dry-uploads";
+const DEFAULT_DOWNLOAD_CONCURRENCY: usize = 4;
+const DEFAULT_HOUSEKEEPING_INTERVAL_SECONDS: u64 = 10 * 60;
+const DEFAULT_DRY_UPLOAD_EVICTION_INTERVAL_MINUTES: u64 = 60;
+const DEFAULT_ESTIMATE_DOWNLOAD_MB_PER_SECOND: f64 = 20.0;
+const DEFAULT_ESTIMATE_UPLOAD_MB_PER_SECOND: f64 = 5.0;
+const DEFAULT_TRANSCODE_CRF: u8 = 28;
+const DEFAULT_TRANSCODE_MAX_HEIGHT: u32 = 720;
+const DEFAULT_TRANSCODE_THREADS: usize = 2;
+const DEFAULT_TRANSCODE_PARALLEL: usize = 2;
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Settings only read at startup, changing them takes a restart
+pub const RESTART_REQUIRED: [&str; 15] = [
+    "upload_backend",
+    "upload_url",
+    "append_file_name",
+    "upload_mirrors",
+    "dry_upload_dir",
+    "dry_upload_ttl",
+    "dry_upload_eviction_interval",
+    "upload_user_agent",
+    "move_list_path",
+    "watch_move_list",
+    "move_aliases_path",
+    "move_threads_path",
+    "clip_registry_path",
+    "exit_on_registration_failure",
+    "housekeeping_interval",
+];
+
+/// How the content type and the extension of an attachment decide whether it's a video
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaPolicy {
+    /// Only the content type Discord tagged it with
+    ContentType,
+    /// Only the file extension
+    Extension,
+    /// Either of them, for files Discord mistags
+    Either,
+    /// Both of them
+    Both,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("expected content_type, extension, either or both")]
+pub struct InvalidMediaPolicy;
+
+impl FromStr for MediaPolicy {
+    type Err = InvalidMediaPolicy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "content_type" => Ok(Self::ContentType),
+            "extension" => Ok(Self::Extension),
+            "either" => Ok(Self::Either),
+            "both" => Ok(Self::Both),
+            _ => Err(InvalidMediaPolicy),
+        }
+    }
+}
+
+/// Which attachments are archived as videos
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaFilter {
+    /// Lowercase content types without parameters
+    pub content_types: Vec<String>,
+    /// Lowercase extensions without the dot
+    pub extensions: Vec<String>,
+    pub policy: MediaPolicy,
+}
+
+impl MediaFilter {
+    pub fn accepts(&self, attachment: &Attachment) -> bool {
+        let content_type = attachment.content_type.as_deref().is_some_and(|ct| {
+            let ct = ct.split(';').next().unwrap_or_default().trim();
+            self.content_types
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(ct))
+        });
+        let extension = Path::new(&attachment.filename)
+            .extension()
+            .and_then(std::ffi::OsStr::to_str)
+            .is_some_and(|ext| self.allows_extension(ext));
+
+        match self.policy {
+            MediaPolicy::ContentType => content_type,
+            MediaPolicy::Extension => extension,
+            MediaPolicy::Either => content_type || extension,
+            MediaPolicy::Both => content_type && extension,
+        }
+    }
+
+    pub fn allows_extension(&self, extension: &str) -> bool {
+        self.extensions
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(extension))
+    }
+}
+
+/// Calls `$callback!` with the name of every setting of `$config`, the destructure fails to compile
+/// when one is missing from the list
+macro_rules! settings {
+    ($config:expr, $callback:ident) => {{
+        let Config {
+            attachment_timeout: _,
+            max_attachments: _,
+            report_webhook_url: _,
+            keep_temp: _,
+            upload_backend: _,
+            upload_url: _,
+            upload_mirrors: _,
+            dry_upload_dir: _,
+            dry_upload_ttl: _,
+            dry_upload_eviction_interval: _,
+            exit_on_registration_failure: _,
+            upload_expires_hours: _,
+            upload_expires_overrides: _,
+            move_list_path: _,
+            watch_move_list: _,
+            move_aliases_path: _,
+            move_threads_path: _,
+            clip_registry_path: _,
+            scan_concurrency: _,
+            thumbnails: _,
+            download_concurrency: _,
+            download_delay: _,
+            housekeeping_interval: _,
+            max_file_size_bytes: _,
+            completion_webhook_url: _,
+            audit_channel_id: _,
+            archive_scan_command: _,
+            guild_daily_quota_bytes: _,
+            estimate_download_mb_per_second: _,
+            estimate_upload_mb_per_second: _,
+            archive_compression: _,
+            stored_extensions: _,
+            transcode: _,
+            duration_filter: _,
+            media_filter: _,
+            include_bots: _,
+            embed_replies: _,
+            upload_user_agent: _,
+            append_file_name: _,
+            download_user_agent: _,
+            webhook_user_agent: _,
+        } = $config;
+        $callback!(
+            attachment_timeout,
+            max_attachments,
+            report_webhook_url,
+            keep_temp,
+            upload_backend,
+            upload_url,
+            upload_mirrors,
+            dry_upload_dir,
+            dry_upload_ttl,
+            dry_upload_eviction_interval,
+            exit_on_registration_failure,
+            upload_expires_hours,
+            upload_expires_overrides,
+            move_list_path,
+            watch_move_list,
+            move_aliases_path,
+            move_threads_path,
+            clip_registry_path,
+            scan_concurrency,
+            thumbnails,
+            download_concurrency,
+            download_delay,
+            housekeeping_interval,
+            max_file_size_bytes,
+            completion_webhook_url,
+            audit_channel_id,
+            archive_scan_command,
+            guild_daily_quota_bytes,
+            estimate_download_mb_per_second,
+            estimate_upload_mb_per_second,
+            archive_compression,
+            stored_extensions,
+            transcode,
+            duration_filter,
+            media_filter,
+            include_bots,
+            embed_replies,
+            upload_user_agent,
+            append_file_name,
+            download_user_agent,
+            webhook_user_agent
+        )
+    }};
+}
+
+/// Settings that hold credentials, only shown as set or not
+const SECRET_SETTINGS: [&str; 2] = ["report_webhook_url", "completion_webhook_url"];
+
+/// Protocol spoken by the upload host
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UploadBackend {
+    /// 0x0.st style multipart form upload, restarted from scratch when it fails
+    ZeroXZero,
+    /// tus resumable uploads
+    Tus,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("expected 0x0 or tus")]
+pub struct InvalidUploadBackend;
+
+impl FromStr for UploadBackend {
+    type Err = InvalidUploadBackend;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "0x0" => Ok(Self::ZeroXZero),
+            "tus" => Ok(Self::Tus),
+            _ => Err(InvalidUploadBackend),
+        }
+    }
+}
+
+/// Settings read from the environment at startup
+pub struct Config {
+    pub attachment_timeout: Duration,
+    pub max_attachments: Option<usize>,
+    pub report_webhook_url: Option<String>,
+    /// Keep each pull's temporary directory around for debugging instead of removing it
+    pub keep_temp: bool,
+    pub upload_backend: UploadBackend,
+    /// Host archives are uploaded to, a tus endpoint when `upload_backend` is tus
+    pub upload_url: String,
+    /// Other hosts each archive is also uploaded to, for a second link if one host purges early
+    pub upload_mirrors: Vec<(UploadBackend, String)>,
+    /// With `DRY_UPLOAD`, archives are copied here instead of being uploaded anywhere
+    pub dry_upload_dir: Option<PathBuf>,
+    /// Archives in `dry_upload_dir` older than this are deleted, they're kept when unset
+    pub dry_upload_ttl: Option<Duration>,
+    /// How often `dry_upload_dir` is checked for archives older than `dry_upload_ttl`
+    pub dry_upload_eviction_interval: Duration,
+    /// Exit at startup if Discord rejects the command registration
+    pub exit_on_registration_failure: bool,
+    pub upload_expires_hours: u64,
+    /// Per move overrides of `upload_expires_hours`
+    pub upload_expires_overrides: HashMap<String, u64>,
+    /// Move list file loaded at startup and checked by `/validate_moves`
+    pub move_list_path: PathBuf,
+    /// Reload the move list when its file changes
+    pub watch_move_list: bool,
+    /// Optional file of `alias=move_name` lines loaded at startup, `/search` matches aliases too
+    pub move_aliases_path: Option<PathBuf>,
+    /// Optional JSON file with the thread of each move, loaded at startup, `/search` links to them
+    pub move_threads_path: Option<PathBuf>,
+    /// File remembering which clips were archived before, for `/pull` with `skip_archived`
+    pub clip_registry_path: PathBuf,
+    /// Segments of a thread whose message pages are fetched in parallel
+    pub scan_concurrency: NonZeroUsize,
+    /// Add the first frame of each clip to the archive, needs ffmpeg installed
+    pub thumbnails: bool,
+    /// Clips larger than this are skipped and listed in the reply
+    pub max_file_size_bytes: Option<u64>,
+    /// Receives a JSON summary of every finished pull
+    pub completion_webhook_url: Option<String>,
+    /// Channel every command invocation is posted to for moderators
+    pub audit_channel_id: Option<ChannelId>,
+    /// Scanner every archive is run through before it's uploaded, e.g. `clamscan --no-summary`
+    pub archive_scan_command: Option<String>,
+    /// Bytes each guild may upload per day, unlimited when unset
+    pub guild_daily_quota_bytes: Option<u64>,
+    /// Download speed `/estimate` assumes, in MB per second
+    pub estimate_download_mb_per_second: f64,
+    /// Upload speed `/estimate` assumes, in MB per second
+    pub estimate_upload_mb_per_second: f64,
+    /// Compression used when `/pull` isn't given one
+    pub archive_compression: Compression,
+    /// Extensions of files written to archives without compressing them, they barely compress
+    pub stored_extensions: Vec<String>,
+    /// Attachments each pull downloads at the same time
+    pub download_concurrency: NonZeroUsize,
+    /// Pause between starting attachment downloads
+    pub download_delay: Duration,
+    /// How often expired entries of the in-memory maps are pruned
+    pub housekeeping_interval: Duration,
+    /// How `/pull` re-encodes clips when asked to, transcoding is disabled when unset
+    pub transcode: Option<TranscodeSettings>,
+    /// Let `/pull` filter clips by length, needs ffprobe installed
+    pub duration_filter: bool,
+    /// Attachments that count as videos
+    pub media_filter: MediaFilter,
+    /// Archive clips posted by bots and webhooks, unless `/pull` says otherwise
+    pub include_bots: bool,
+    /// Reply to successful pulls with an embed instead of plain text
+    pub embed_replies: bool,
+    /// User-Agent of the requests to the upload host
+    pub upload_user_agent: String,
+    /// Append the archive's file name to 0x0.st links, some download managers can't handle it
+    pub append_file_name: bool,
+    /// User-Agent of the attachment downloads from the Discord CDN
+    pub download_user_agent: String,
+    /// User-Agent of the completion webhook requests
+    pub webhook_user_agent: String,
+}
+
+impl Config {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let user_agent =
+            user_agent_var("USER_AGENT")?.unwrap_or_else(|| DEFAULT_USER_AGENT.to_owned());
+        Ok(Self {
+            attachment_timeout: Duration::from_secs(
+                parse_var("ATTACHMENT_TIMEOUT_SECONDS")?
+                    .unwrap_or(DEFAULT_ATTACHMENT_TIMEOUT_SECONDS),
+            ),
+            // a limit of 0 would make every pull stop before its first clip
+            max_attachments: parse_var::<NonZeroUsize>("MAX_ATTACHMENTS")?.map(NonZeroUsize::get),
+            report_webhook_url: std::env::var("REPORT_WEBHOOK_URL").ok(),
+            keep_temp: parse_var("KEEP_TEMP")?.unwrap_or(false),
+            upload_backend: parse_var("UPLOAD_BACKEND")?.unwrap_or(UploadBackend::ZeroXZero),
+            upload_url: parse_url_var("UPLOAD_URL")?
+                .unwrap_or_else(|| DEFAULT_UPLOAD_URL.to_owned()),
+            upload_mirrors: parse_mirrors_var("UPLOAD_MIRRORS")?,
+            dry_upload_dir: parse_var("DRY_UPLOAD")?.unwrap_or(false).then(|| {
+                std::env::var("DRY_UPLOAD_DIR")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|_| PathBuf::from(DEFAULT_DRY_UPLOAD_DIR))
+            }),
+            dry_upload_ttl: parse_var::<NonZeroU64>("DRY_UPLOAD_TTL_HOURS")?
+                .map(|hours| Duration::from_secs(hours.get() * 60 * 60)),
+            dry_upload_eviction_interval: Duration::from_secs(
+                parse_var::<NonZeroU64>("DRY_UPLOAD_EVICTION_INTERVAL_MINUTES")?.map_or(
+                    DEFAULT_DRY_UPLOAD_EVICTION_INTERVAL_MINUTES,
+                    NonZeroU64::get,
+                ) * 60,
+            ),
+            exit_on_registration_failure: parse_var("EXIT_ON_REGISTRATION_FAILURE")?
+                .unwrap_or(false),
+            upload_expires_hours: parse_var("UPLOAD_EXPIRES_HOURS")?
+                .unwrap_or(DEFAULT_UPLOAD_EXPIRES_HOURS),
+            upload_expires_overrides: parse_map_var("UPLOAD_EXPIRES_OVERRIDES")?,
+            move_list_path: std::env::var("MOVE_LIST_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from(DEFAULT_MOVE_LIST_PATH)),
+            watch_move_list: parse_var("WATCH_MOVE_LIST")?.unwrap_or(false),
+            move_aliases_path: std::env::var("MOVE_ALIASES_PATH").ok().map(PathBuf::from),
+            move_threads_path: std::env::var("MOVE_THREADS_PATH").ok().map(PathBuf::from),
+            clip_registry_path: std::env::var("CLIP_REGISTRY_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from(DEFAULT_CLIP_REGISTRY_PATH)),
+            scan_concurrency: parse_var("SCAN_CONCURRENCY")?.unwrap_or(NonZeroUsize::MIN),
+            thumbnails: parse_var("THUMBNAILS")?.unwrap_or(false),
+            completion_webhook_url: parse_url_var("COMPLETION_WEBHOOK_URL")?,
+            archive_scan_command: std::env::var("ARCHIVE_SCAN_COMMAND").ok(),
+            audit_channel_id: parse_var::<NonZeroU64>("AUDIT_CHANNEL_ID")?.map(ChannelId::from),
+            archive_compression: parse_var("ARCHIVE_COMPRESSION")?.unwrap_or_default(),
+            // set but empty compresses every file
+            stored_extensions: match std::env::var("STORED_EXTENSIONS") {
+                Ok(value) if value.trim().is_empty() => Vec::new(),
+                _ => list_var("STORED_EXTENSIONS")?
+                    .unwrap_or_else(|| DEFAULT_STORED_EXTENSIONS.map(str::to_owned).to_vec()),
+            },
+            guild_daily_quota_bytes: parse_var::<u64>("GUILD_DAILY_QUOTA_MB")?
+                .map(|mb| mb * 1024 * 1024),
+            estimate_download_mb_per_second: rate_var(
+                "ESTIMATE_DOWNLOAD_MB_PER_SECOND",
+                DEFAULT_ESTIMATE_DOWNLOAD_MB_PER_SECOND,
+            )?,
+            estimate_upload_mb_per_second: rate_var(
+                "ESTIMATE_UPLOAD_MB_PER_SECOND",
+                DEFAULT_ESTIMATE_UPLOAD_MB_PER_SECOND,
+            )?,
+            max_file_size_bytes: parse_var::<u64>("MAX_FILE_SIZE_MB")?.map(|mb| mb * 1024 * 1024),
+            download_concurrency: parse_var("DOWNLOAD_CONCURRENCY")?.unwrap_or(
+                NonZeroUsize::new(DEFAULT_DOWNLOAD_CONCURRENCY).expect("default is not zero"),
+            ),
+            download_delay: Duration::from_millis(parse_var("DOWNLOAD_DELAY_MS")?.unwrap_or(0)),
+            housekeeping_interval: Duration::from_secs(
+                parse_var::<NonZeroU64>("HOUSEKEEPING_INTERVAL_SECONDS")?
+                    .map_or(DEFAULT_HOUSEKEEPING_INTERVAL_SECONDS, NonZeroU64::get),
+            ),
+            transcode: transcode_settings()?,
+            duration_filter: parse_var("DURATION_FILTER")?.unwrap_or(false),
+            embed_replies: parse_var("EMBED_REPLIES")?.unwrap_or(true),
+            include_bots: parse_var("INCLUDE_BOTS")?.unwrap_or(true),
+            media_filter: MediaFilter {
+                content_types: list_var("ALLOWED_CONTENT_TYPES")?
+                    .unwrap_or_else(|| DEFAULT_ALLOWED_CONTENT_TYPES.map(str::to_owned).to_vec()),
+                extensions: list_var("ALLOWED_EXTENSIONS")?
+                    .map(|extensions| {
+                        extensions
+                            .into_iter()
+                            .map(|ext| ext.trim_start_matches('.').to_owned())
+                            .collect()
+                    })
+                    .unwrap_or_else(|| DEFAULT_ALLOWED_EXTENSIONS.map(str::to_owned).to_vec()),
+                policy: parse_var("MEDIA_POLICY")?.unwrap_or(MediaPolicy::ContentType),
+            },
+            upload_user_agent: user_agent_var("UPLOAD_USER_AGENT")?
+                .unwrap_or_else(|| user_agent.clone()),
+            append_file_name: parse_var("APPEND_FILE_NAME")?.unwrap_or(true),
+            download_user_agent: user_agent_var("DOWNLOAD_USER_AGENT")?
+                .unwrap_or_else(|| user_agent.clone()),
+            webhook_user_agent: user_agent_var("WEBHOOK_USER_AGENT")?.unwrap_or(user_agent),
+        })
+    }
+
+    /// Names of the settings that differ in `other`
+    pub fn changed(&self, other: &Config) -> Vec<&'static str> {
+        macro_rules! changed {
+            ($($field:ident),*) => {
+                [$((stringify!($field), self.$field != other.$field)),*]
+                    .into_iter()
+                    .filter_map(|(name, changed)| changed.then_some(name))
+                    .collect()
+            };
+        }
+        settings!(self, changed)
+    }
+
+    /// Every setting with its value, the secret ones only say whether they're set
+    pub fn redacted(&self) -> Vec<(&'static str, String)> {
+        macro_rules! redacted {
+            ($($field:ident),*) => {
+                vec![$((stringify!($field), format!("{:?}", self.$field))),*]
+            };
+        }
+        let mut settings: Vec<(&'static str, String)> = settings!(self, redacted);
+        for (name, value) in &mut settings {
+            if SECRET_SETTINGS.contains(name) && value != "None" {
+                *value = "(set)".to_owned();
+            }
+        }
+        settings
+    }
+
+    /// Hours until the download link of `move_name` expires
+    pub fn upload_expires_hours(&self, move_name: &str) -> u64 {
+        self.upload_expires_overrides
+            .get(move_name)
+            .copied()
+            .unwrap_or(self.upload_expires_hours)
+    }
+}
+
+/// Outcome of one of the `--check-config` validations
+pub struct Check {
+    pub name: &'static str,
+    /// What was found on success
+    pub result: anyhow::Result<String>,
+}
+
+/// Runs the startup validations without connecting to Discord
+pub async fn check() -> Vec<Check> {
+    let mut checks = vec![Check {
+        name: "Discord token",
+        result: std::env::var("DISCORD_TOKEN")
+            .context("DISCORD_TOKEN not set")
+            .and_then(|token| {
+                poise::serenity_prelude::utils::validate_token(token.trim())
+                    .context("DISCORD_TOKEN is malformed")
+            })
+            .map(|()| "set and well-formed".to_owned()),
+    }];
+
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            checks.push(Check {
+                name: "Configuration",
+                result: Err(e),
+            });
+            return checks;
+        }
+    };
+    checks.push(Check {
+        name: "Configuration",
+        result: Ok("valid".to_owned()),
+    });
+
+    checks.push(Check {
+        name: "Move list",
+        result: crate::moves::load_or_builtin(&config.move_list_path)
+            .context(format!(
+                "Failed to read {}",
+                config.move_list_path.display()
+            ))
+            .and_then(|(moves, source)| {
+                anyhow::ensure!(
+                    !moves.is_empty(),
+                    "{} is empty",
+                    config.move_list_path.display()
+                );
+                Ok(match source {
+                    MoveListSource::File => format!(
+                        "{} moves from {}",
+                        moves.len(),
+                        config.move_list_path.display()
+                    ),
+                    MoveListSource::Builtin => format!(
+                        "{} moves built into the bot, {} doesn't exist",
+                        moves.len(),
+                        config.move_list_path.display()
+                    ),
+                })
+            }),
+    });
+
+    checks.push(Check {
+        name: "Upload host",
+        result: check_reachable(&config.upload_url, &config.upload_user_agent).await,
+    });
+
+    checks.push(Check {
+        name: "Temporary directory",
+        result: tempfile::tempdir()
+            .and_then(|dir| std::fs::write(dir.path().join("check"), b"check"))
+            .map(|()| format!("{} is writable", std::env::temp_dir().display()))
+            .context("Failed to write a temporary file"),
+    });
+
+    checks
+}
+
+/// Any response counts, the host only has to answer
+async fn check_reachable(url: &str, user_agent: &str) -> anyhow::Result<String> {
+    let response = reqwest::Client::builder()
+        .user_agent(user_agent)
+        .timeout(CHECK_TIMEOUT)
+        .build()
+        .context("Failed to create HTTP client")?
+        .head(url)
+        .send()
+        .await
+        .context(format!("{} is unreachable", url))?;
+    Ok(format!("{} answered {}", url, response.status()))
+}
+
+/// Parses an optional environment variable, failing if it's set but invalid
+fn parse_var<T>(name: &str) -> anyhow::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match std::env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .context(format!("Invalid value for {}", name)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Reads an optional environment variable, failing if it's set but not a valid url
+fn parse_url_var(name: &str) -> anyhow::Result<Option<String>> {
+    match std::env::var(name) {
+        Ok(value) => {
+            let value = value.trim();
+            anyhow::ensure!(value.validate_url(), "Invalid url for {}", name);
+            Ok(Some(value.to_owned()))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+/// Reads an optional speed in MB per second, failing unless it's positive
+fn rate_var(name: &str, default: f64) -> anyhow::Result<f64> {
+    let rate = parse_var::<f64>(name)?.unwrap_or(default);
+    anyhow::ensure!(
+        rate.is_finite() && rate > 0.0,
+        "Invalid value for {}, expected a positive number",
+        name
+    );
+    Ok(rate)
+}
+
+/// Transcoding settings if `TRANSCODE` is enabled
+fn transcode_settings() -> anyhow::Result<Option<TranscodeSettings>> {
+    if !parse_var("TRANSCODE")?.unwrap_or(false) {
+        return Ok(None);
+    }
+
+    let crf = parse_var("TRANSCODE_CRF")?.unwrap_or(DEFAULT_TRANSCODE_CRF);
+    anyhow::ensure!(
+        crf <= 51,
+        "Invalid value for TRANSCODE_CRF, expected 0 to 51"
+    );
+    Ok(Some(TranscodeSettings {
+        crf,
+        max_height: parse_var("TRANSCODE_MAX_HEIGHT")?.unwrap_or(DEFAULT_TRANSCODE_MAX_HEIGHT),
+        threads: parse_var("TRANSCODE_THREADS")?
+            .unwrap_or(NonZeroUsize::new(DEFAULT_TRANSCODE_THREADS).expect("default is not zero")),
+        parallel: parse_var("TRANSCODE_PARALLEL")?
+            .unwrap_or(NonZeroUsize::new(DEFAULT_TRANSCODE_PARALLEL).expect("default is not zero")),
+    }))
+}
+
+/// Reads an optional environment variable, failing if it can't be sent as a header
+fn user_agent_var(name: &str) -> anyhow::Result<Option<String>> {
+    match std::env::var(name) {
+        Ok(value) => {
+            let value = value.trim();
+            anyhow::ensure!(
+                !value.is_empty() && reqwest::header::HeaderValue::from_str(value).is_ok(),
+                "Invalid user agent for {}",
+                name
+            );
+            Ok(Some(value.to_owned()))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+/// Reads an optional environment variable of comma separated values, lowercased,
+/// failing if it's set without any
+fn list_var(name: &str) -> anyhow::Result<Option<Vec<String>>> {
+    let Ok(value) = std::env::var(name) else {
+        return Ok(None);
+    };
+
+    let items: Vec<String> = value
+        .split(',')
+        .map(|item| item.trim().to_lowercase())
+        .filter(|item| !item.is_empty())
+        .collect();
+    anyhow::ensure!(!items.is_empty(), "{} is set but empty", name);
+    Ok(Some(items))
+}
+
+/// Parses an optional environment variable of comma separated `backend=url` pairs,
+/// the same backend can be listed more than once
+fn parse_mirrors_var(name: &str) -> anyhow::Result<Vec<(UploadBackend, String)>> {
+    let Ok(value) = std::env::var(name) else {
+        return Ok(Vec::new());
+    };
+
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (backend, url) = pair
+                .split_once('=')
+                .context(format!("Expected backend=url pairs in {}", name))?;
+            let backend = backend
+                .trim()
+                .parse()
+                .context(format!("Invalid backend {} in {}", backend, name))?;
+            let url = url.trim();
+            anyhow::ensure!(url.validate_url(), "Invalid url {} in {}", url, name);
+            Ok((backend, url.to_owned()))
+        })
+        .collect()
+}
+
+/// Parses an optional environment variable of comma separated `key=value` pairs
+fn parse_map_var<T>(name: &str) -> anyhow::Result<HashMap<String, T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let Ok(value) = std::env::var(name) else {
+        return Ok(HashMap::new());
+    };
+
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair
+                .split_once('=')
+                .context(format!("Expected key=value pairs in {}", name))?;
+            let value = value
+                .trim()
+                .parse()
+                .context(format!("Invalid value for {} in {}", key, name))?;
+            Ok((key.trim().to_owned(), value))
+        })
+        .collect()
+}
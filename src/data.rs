@@ -0,0 +1,182 @@
+use crate::config::Config;
+use crate::error::BotError;
+use crate::history::{History, PullMarkers, RecentUploads};
+use crate::housekeeping::SharedExpiring;
+use crate::quota::UploadQuota;
+use crate::registry::ClipRegistry;
+use crate::upload::Uploader;
+use arc_swap::ArcSwap;
+use poise::serenity_prelude::{ChannelId, UserId};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+pub struct Data {
+    /// Swapped by the move list watcher when `WATCH_MOVE_LIST` is set
+    pub move_list: Arc<ArcSwap<Vec<String>>>,
+    /// Aliases of each move, `/search` matches them too
+    pub move_aliases: HashMap<String, Vec<String>>,
+    /// Swapped by `/reload_config`, commands take a snapshot when they start
+    pub config: ArcSwap<Config>,
+    pub uploader: Box<dyn Uploader>,
+    pub started_at: Instant,
+    pub metrics: Metrics,
+    /// Last command each user ran, attached to their reports
+    pub last_commands: Mutex<HashMap<UserId, String>>,
+    pub history: Mutex<History>,
+    pub pull_markers: Mutex<PullMarkers>,
+    /// Shared with the housekeeping task, which prunes it
+    pub upload_quota: Arc<Mutex<UploadQuota>>,
+    /// Shared with the housekeeping task, which prunes it
+    pub recent_uploads: Arc<Mutex<RecentUploads>>,
+    pub clip_registry: Mutex<ClipRegistry>,
+    /// Thread of each move from `MOVE_THREADS_PATH` or seen by `/pull` or `/missing` since startup
+    move_threads: Mutex<HashMap<String, ChannelId>>,
+    /// Shared with the shutdown handler, which waits for them
+    pub background_pulls: Arc<BackgroundPulls>,
+}
+
+impl Data {
+    pub fn new(
+        move_list: Vec<String>,
+        move_aliases: HashMap<String, Vec<String>>,
+        config: Config,
+        uploader: Box<dyn Uploader>,
+        clip_registry: ClipRegistry,
+        move_threads: HashMap<String, ChannelId>,
+        background_pulls: Arc<BackgroundPulls>,
+    ) -> Self {
+        Self {
+            move_list: Arc::new(ArcSwap::from_pointee(move_list)),
+            move_aliases,
+            config: ArcSwap::from_pointee(config),
+            uploader,
+            started_at: Instant::now(),
+            metrics: Metrics::default(),
+            last_commands: Mutex::new(HashMap::new()),
+            history: Mutex::new(History::default()),
+            pull_markers: Mutex::new(PullMarkers::default()),
+            upload_quota: Arc::new(Mutex::new(UploadQuota::default())),
+            recent_uploads: Arc::new(Mutex::new(RecentUploads::default())),
+            clip_registry: Mutex::new(clip_registry),
+            move_threads: Mutex::new(move_threads),
+            background_pulls,
+        }
+    }
+}
+
+impl Data {
+    /// Thread of `move_name`, `/search` links to it
+    pub fn move_thread(&self, move_name: &str) -> Option<ChannelId> {
+        self.move_threads.lock().unwrap().get(move_name).copied()
+    }
+
+    /// Remembers `thread_id` as the thread of `move_name` unless it already has one,
+    /// so threads from `MOVE_THREADS_PATH` are kept
+    pub fn remember_move_thread(&self, move_name: &str, thread_id: ChannelId) {
+        self.move_threads
+            .lock()
+            .unwrap()
+            .entry(move_name.to_owned())
+            .or_insert(thread_id);
+    }
+
+    /// Maps the housekeeping task prunes
+    pub fn expiring(&self) -> Vec<(&'static str, SharedExpiring)> {
+        let mut maps: Vec<(&'static str, SharedExpiring)> = vec![
+            ("upload quota", self.upload_quota.clone()),
+            ("recent uploads", self.recent_uploads.clone()),
+        ];
+        maps.extend(self.uploader.expiring());
+        maps
+    }
+}
+
+/// Counters since startup
+#[derive(Default)]
+pub struct Metrics {
+    pub pulls_served: AtomicU64,
+    pub active_pulls: AtomicU64,
+    pub bytes_uploaded: AtomicU64,
+    pub searches: AtomicU64,
+    pub failures: AtomicU64,
+}
+
+impl Metrics {
+    pub fn increment(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Pulls running after their user was told they'd get a DM, so a shutdown doesn't drop them
+#[derive(Default)]
+pub struct BackgroundPulls {
+    /// Description of each running pull by invocation id
+    running: Mutex<HashMap<u64, String>>,
+    closing: AtomicBool,
+    finished: Notify,
+}
+
+impl BackgroundPulls {
+    /// Registers a pull until the returned guard is dropped, `None` once the bot is shutting down
+    pub fn start(&self, id: u64, description: String) -> Option<BackgroundPull<'_>> {
+        let mut running = self.running.lock().unwrap();
+        if self.closing.load(Ordering::Relaxed) {
+            return None;
+        }
+        running.insert(id, description);
+        Some(BackgroundPull { pulls: self, id })
+    }
+
+    /// Refuses new pulls and waits up to `timeout` for the running ones to finish,
+    /// returns the descriptions of those still running
+    pub async fn drain(&self, timeout: Duration) -> Vec<String> {
+        self.closing.store(true, Ordering::Relaxed);
+        let _ = tokio::time::timeout(timeout, async {
+            loop {
+                // created before checking, so a pull finishing in between still wakes it
+                let finished = self.finished.notified();
+                if self.running.lock().unwrap().is_empty() {
+                    break;
+                }
+                finished.await;
+            }
+        })
+        .await;
+        self.running.lock().unwrap().values().cloned().collect()
+    }
+}
+
+/// Keeps a background pull registered for as long as it's alive
+pub struct BackgroundPull<'a> {
+    pulls: &'a BackgroundPulls,
+    id: u64,
+}
+
+impl Drop for BackgroundPull<'_> {
+    fn drop(&mut self) {
+        self.pulls.running.lock().unwrap().remove(&self.id);
+        self.pulls.finished.notify_waiters();
+    }
+}
+
+pub type Error = BotError;
+pub type Context<'a> = poise::Context<'a, Data, Error>;
+
+/// Counts a pull as active for as long as it's alive
+pub struct ActivePull<'a>(&'a AtomicU64);
+
+impl<'a> ActivePull<'a> {
+    pub fn new(counter: &'a AtomicU64) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for ActivePull<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
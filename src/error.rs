@@ -0,0 +1,61 @@
+use crate::archive::TaskError;
+use crate::data::{Data, Error, Metrics};
+use crate::reply;
+use poise::serenity_prelude as serenity;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BotError {
+    #[error("Failed to scan thread: {0:#}")]
+    Scan(anyhow::Error),
+    #[error("Failed to create archive: {0:#}")]
+    Archive(anyhow::Error),
+    #[error("Archive task panicked: {0}")]
+    ArchivePanicked(String),
+    #[error("Failed to upload archive: {0:#}")]
+    Upload(anyhow::Error),
+    #[error("Discord request failed: {0}")]
+    Discord(#[from] serenity::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<TaskError> for BotError {
+    fn from(error: TaskError) -> Self {
+        match error {
+            TaskError::Failed(e) => BotError::Archive(e),
+            TaskError::Panicked(message) => BotError::ArchivePanicked(message),
+        }
+    }
+}
+
+pub async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
+    match error {
+        poise::FrameworkError::Command { error, ctx, .. } => {
+            tracing::error!("Command {} failed: {}", ctx.command().name, error);
+            Metrics::increment(&ctx.data().metrics.failures);
+
+            let reply = match error {
+                BotError::Scan(_) => {
+                    "Failed to read the messages in this thread, make sure I can view it and read its history"
+                }
+                BotError::Archive(_) => "Failed to download the videos, please try again later",
+                BotError::ArchivePanicked(_) => {
+                    "Making the archive crashed, please try again later and `/report` it if it keeps happening"
+                }
+                BotError::Upload(_) => "Failed to upload the archive, please try again later",
+                BotError::Discord(_) | BotError::Other(_) => {
+                    "Something went wrong, please try again later"
+                }
+            };
+
+            if let Err(e) = reply::send_text(ctx, reply).await {
+                tracing::error!("Failed to send error message: {}", e);
+            }
+        }
+        error => {
+            if let Err(e) = poise::builtins::on_error(error).await {
+                tracing::error!("Failed to handle error: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::housekeeping::Expiring;
+use poise::serenity_prelude::{ChannelId, GuildId, MessageId, Timestamp, UserId};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const MAX_HISTORY_RECORDS: usize = 100;
+/// How long a retried pull of the same clips gets the earlier link instead of a new upload
+const RECENT_UPLOAD_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+pub struct UploadRecord {
+    /// Moves whose clips are in the archive, more than one for a combined `/pull_many`
+    pub moves: Vec<String>,
+    /// Url returned by the upload host
+    pub url: String,
+    /// Link given to the user
+    pub link: String,
+    /// Management token returned by 0x0.st, needed to delete the upload
+    pub token: Option<String>,
+    /// Archive kept on disk in keep-temp mode
+    pub archive: Option<PathBuf>,
+    /// Number of clips in the archive
+    pub clips: usize,
+    /// User who ran the pull
+    pub user_id: UserId,
+    pub guild_id: Option<GuildId>,
+    pub uploaded_at: Timestamp,
+    pub expires_hours: u64,
+}
+
+impl UploadRecord {
+    pub fn expires_at(&self) -> i64 {
+        self.uploaded_at.unix_timestamp() + self.expires_hours as i64 * 60 * 60
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Timestamp::now().unix_timestamp() >= self.expires_at()
+    }
+}
+
+/// Most recent uploads of this session, oldest are dropped first
+#[derive(Default)]
+pub struct History {
+    records: VecDeque<UploadRecord>,
+}
+
+impl History {
+    pub fn push(&mut self, record: UploadRecord) {
+        if self.records.len() == MAX_HISTORY_RECORDS {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+
+    /// Most recent upload with `move_name` by `user_id` in `guild_id` whose archive is still on disk.
+    /// Archives of other users or servers may have been filtered for them, so they're never reused
+    pub fn latest_archive(
+        &self,
+        move_name: &str,
+        guild_id: Option<GuildId>,
+        user_id: UserId,
+    ) -> Option<&UploadRecord> {
+        self.records.iter().rev().find(|record| {
+            record.moves.iter().any(|name| name == move_name)
+                && record.guild_id == guild_id
+                && record.user_id == user_id
+                && record.archive.as_deref().is_some_and(Path::exists)
+        })
+    }
+
+    /// Most recent uploads first
+    pub fn recent(&self) -> impl Iterator<Item = &UploadRecord> {
+        self.records.iter().rev()
+    }
+
+    /// Upload in `guild_id` whose url or link is `link`
+    pub fn find_by_link(&self, link: &str, guild_id: Option<GuildId>) -> Option<&UploadRecord> {
+        self.records.iter().find(|record| {
+            record.guild_id == guild_id && (record.url == link || record.link == link)
+        })
+    }
+
+    /// Removes and returns the upload in `guild_id` whose url or link is `link`
+    pub fn remove_by_link(
+        &mut self,
+        link: &str,
+        guild_id: Option<GuildId>,
+    ) -> Option<UploadRecord> {
+        let index = self.records.iter().position(|record| {
+            record.guild_id == guild_id && (record.url == link || record.link == link)
+        })?;
+        self.records.remove(index)
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+pub struct PullMarkerKey {
+    pub user_id: UserId,
+    pub channel_id: ChannelId,
+    pub move_name: String,
+}
+
+/// Newest message scanned by each user's last pull of a move in a thread
+#[derive(Default)]
+pub struct PullMarkers {
+    markers: HashMap<PullMarkerKey, MessageId>,
+}
+
+impl PullMarkers {
+    pub fn get(&self, key: &PullMarkerKey) -> Option<MessageId> {
+        self.markers.get(key).copied()
+    }
+
+    pub fn set(&mut self, key: PullMarkerKey, message_id: MessageId) {
+        self.markers.insert(key, message_id);
+    }
+}
+
+/// Identifies a pull by who ran it and what it archived
+#[derive(PartialEq, Eq, Hash)]
+pub struct UploadKey {
+    pub user_id: UserId,
+    pub move_name: String,
+    /// Hash of the archived attachments and the options that change the archive
+    pub contents: u64,
+}
+
+/// Links of recent uploads, so a pull retried because its reply never arrived doesn't upload again
+#[derive(Default)]
+pub struct RecentUploads {
+    /// When each pull was uploaded, its link and the reply with it
+    uploads: HashMap<UploadKey, (Instant, String, String)>,
+}
+
+impl RecentUploads {
+    pub fn get(&mut self, key: &UploadKey) -> Option<String> {
+        self.prune();
+        self.uploads.get(key).map(|(_, _, reply)| reply.clone())
+    }
+
+    /// Remembers the link of the upload and the reply with it
+    pub fn insert(&mut self, key: UploadKey, link: String, reply: String) {
+        self.uploads.insert(key, (Instant::now(), link, reply));
+    }
+
+    /// Forgets the uploads with `link`, so a deleted upload isn't handed out again
+    pub fn remove_link(&mut self, link: &str) {
+        self.uploads.retain(|_, (_, uploaded, _)| uploaded != link);
+    }
+}
+
+impl Expiring for RecentUploads {
+    fn prune(&mut self) -> usize {
+        let before = self.uploads.len();
+        self.uploads
+            .retain(|_, (uploaded_at, _, _)| uploaded_at.elapsed() < RECENT_UPLOAD_WINDOW);
+        before - self.uploads.len()
+    }
+}
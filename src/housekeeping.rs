@@ -0,0 +1,29 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// In-memory state with entries that expire, swept by [`spawn`] so it doesn't grow for as long as
+/// the bot runs
+pub trait Expiring {
+    /// Drops the expired entries, returns how many were dropped
+    fn prune(&mut self) -> usize;
+}
+
+pub type SharedExpiring = Arc<Mutex<dyn Expiring + Send>>;
+
+/// Prunes each of `maps` every `interval` in the background
+pub fn spawn(interval: Duration, maps: Vec<(&'static str, SharedExpiring)>) {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        // the first tick completes immediately, nothing has expired yet
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            for (name, map) in &maps {
+                let pruned = map.lock().unwrap().prune();
+                if pruned > 0 {
+                    tracing::debug!("Pruned {} expired entries of {}", pruned, name);
+                }
+            }
+        }
+    });
+}
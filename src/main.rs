@@ -1,303 +1,212 @@
-use anyhow::Context as AnyhowContext;
-use poise::CreateReply;
-use poise::serenity_prelude::{self as serenity, Attachment, ChannelType, GetMessages, MessageId};
-use std::path::{Path, PathBuf};
-use tracing::instrument;
+mod antivirus;
+mod archive;
+mod audit;
+mod commands;
+mod config;
+mod data;
+mod error;
+mod history;
+mod housekeeping;
+mod moves;
+mod probe;
+mod process;
+mod quota;
+mod registry;
+mod reply;
+mod scan;
+mod thumbnail;
+mod transcode;
+mod upload;
+mod watcher;
+mod webhook;
+
+#[cfg(test)]
+mod tests;
+
+use config::{Config, UploadBackend};
+use data::{BackgroundPulls, Data};
+use poise::serenity_prelude as serenity;
+use registry::ClipRegistry;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing_subscriber::{fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt};
-use validator::ValidateUrl;
+use upload::{DryUpload, MirrorUploader, Tus, Uploader, ZeroXZero};
 
-struct Data {
-    move_list: Vec<&'static str>,
-}
-type Error = Box<dyn std::error::Error + Send + Sync>;
-type Context<'a> = poise::Context<'a, Data, Error>;
+/// Longest a shutdown waits for background pulls to send their links
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 
-const ALLOWED_CONTENT_TYPES: [&str; 2] = ["video/quicktime", "video/mp4"];
-const FILE_UPLOAD_URL: &str = "https://0x0.st";
-const MAX_TOTAL_SIZE_BYTES: u64 = 512 * 1024 * 1024; // 512MB
-const USER_AGENT: &str = "GsohDiscordBot/1.0 (https://github.com/tufourn/gsoh-discord-bot)";
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
 
-#[poise::command(slash_command)]
-#[instrument(name = "pull", skip_all, fields(id = ctx.id(), username = ctx.author().name, move_name = move_name))]
-async fn pull(
-    ctx: Context<'_>,
-    #[description = "Move name"] move_name: String,
-) -> Result<(), Error> {
-    ctx.defer_ephemeral()
-        .await
-        .context("Failed to defer response")?;
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "info,serenity=error".into()),
+        )
+        .with(tracing_subscriber::fmt::layer().with_span_events(FmtSpan::NEW | FmtSpan::CLOSE))
+        .init();
 
-    let guild_channel = match ctx.guild_channel().await {
-        Some(gc) => match gc.kind {
-            ChannelType::NewsThread | ChannelType::PublicThread | ChannelType::PrivateThread => gc,
-            _ => {
-                ctx.send(CreateReply {
-                    content: Some("This command must be run in a thread".to_owned()),
-                    ephemeral: Some(true),
-                    ..Default::default()
-                })
-                .await
-                .context("Failed to send message")?;
-                return Ok(());
+    if std::env::args().skip(1).any(|arg| arg == "--check-config") {
+        let checks = config::check().await;
+        for check in &checks {
+            match &check.result {
+                Ok(found) => println!("ok    {}: {}", check.name, found),
+                Err(e) => println!("FAIL  {}: {:#}", check.name, e),
             }
-        },
-        None => {
-            ctx.send(CreateReply {
-                content: Some("This command must be run in a thread".to_owned()),
-                ephemeral: Some(true),
-                ..Default::default()
-            })
-            .await
-            .context("Failed to send message")?;
-            return Ok(());
         }
-    };
-
-    if !ctx.data().move_list.contains(&move_name.as_str()) {
-        ctx.send(CreateReply {
-            content: Some(
-                "Move not found, use `/search <page_number>` to get the move name".to_owned(),
-            ),
-            ephemeral: Some(true),
-            ..Default::default()
-        })
-        .await
-        .context("Failed to send message")?;
-        return Ok(());
-    }
-
-    struct Submission {
-        attachment: Attachment,
-        username: String,
+        let passed = checks.iter().all(|check| check.result.is_ok());
+        std::process::exit(if passed { 0 } else { 1 });
     }
-    let mut submissions: Vec<Submission> = Vec::new();
 
-    let mut last_message_id: Option<MessageId> = None;
-    loop {
-        let mut builder = GetMessages::new().limit(100);
-        if let Some(id) = last_message_id {
-            builder = builder.before(id);
-        }
+    let token = std::env::var("DISCORD_TOKEN").expect("DISCORD_TOKEN not set");
+    let intents =
+        serenity::GatewayIntents::non_privileged() | serenity::GatewayIntents::MESSAGE_CONTENT;
 
-        let messages = guild_channel
-            .id
-            .messages(&ctx, builder)
-            .await
-            .context("Failed to retrieve messages")?;
-        if messages.is_empty() {
-            break;
+    let config = Config::from_env().expect("Invalid configuration");
+    let uploader: Box<dyn Uploader> = if let Some(dir) = &config.dry_upload_dir {
+        tracing::warn!(
+            "DRY_UPLOAD is set, archives are copied to {} instead of being uploaded",
+            dir.display()
+        );
+        let dry_upload =
+            DryUpload::new(dir.clone()).expect("Failed to create the dry upload directory");
+        if let Some(ttl) = config.dry_upload_ttl {
+            dry_upload.spawn_eviction(ttl, config.dry_upload_eviction_interval);
         }
-
-        last_message_id = messages.last().map(|m| m.id);
-        for message in messages {
-            for attachment in message.attachments {
-                if attachment
-                    .content_type
-                    .as_deref()
-                    .is_some_and(|ct| ALLOWED_CONTENT_TYPES.contains(&ct))
-                {
-                    submissions.push(Submission {
-                        attachment,
-                        username: message.author.name.to_owned(),
-                    });
-                }
-            }
+        Box::new(dry_upload)
+    } else if config.upload_mirrors.is_empty() {
+        new_uploader(config.upload_backend, &config.upload_url, &config)
+    } else {
+        let primary = new_uploader(config.upload_backend, &config.upload_url, &config);
+        let mut hosts = vec![(config.upload_url.clone(), primary)];
+        for (backend, url) in &config.upload_mirrors {
+            hosts.push((url.clone(), new_uploader(*backend, url, &config)));
         }
-    }
-
-    if submissions.is_empty() {
-        ctx.send(CreateReply {
-            content: Some("No video (.mov or .mp4) found".to_owned()),
-            ephemeral: Some(true),
-            ..Default::default()
-        })
-        .await
-        .context("Failed to send message")?;
-        return Ok(());
-    }
-
-    let dir = tempfile::tempdir().context("Failed to create temporary directory")?;
-    let zip_file_name = format!("{}.zip", &move_name);
-    let zip_file_path = dir.path().join(&zip_file_name);
-
-    struct ArchiveResult {
-        archive: PathBuf,
-        message: Option<String>,
-    }
-
-    let archive_result = tokio::task::spawn_blocking(move || {
-        let zip_file = std::fs::File::create(&zip_file_path).context("Failed to create archive")?;
-        let mut zip = zip::ZipWriter::new(zip_file);
-        let options = zip::write::SimpleFileOptions::default()
-            .compression_method(zip::CompressionMethod::Deflated);
-
-        let mut total_size = 0;
-        let mut message = None;
-
-        for submission in submissions {
-            let file_extension = match Path::new(&submission.attachment.filename)
-                .extension()
-                .and_then(std::ffi::OsStr::to_str)
-            {
-                Some(ext) => ext,
-                None => continue,
-            };
-
-            if total_size + submission.attachment.size as u64 > MAX_TOTAL_SIZE_BYTES {
-                message = Some(format!(
-                    "Size limit 512MB reached. Messages from {} and earlier were not downloaded",
-                    submission.attachment.id.created_at()
-                ));
-                break;
-            }
-            total_size += submission.attachment.size as u64;
+        tracing::info!("Uploading every archive to {} hosts", hosts.len());
+        Box::new(MirrorUploader::new(hosts))
+    };
 
-            let new_file_name = format!(
-                "{}-{}-{}.{}",
-                &move_name, &submission.username, submission.attachment.id, file_extension
+    let move_list = moves::load_or_builtin(&config.move_list_path)
+        .map(|(moves, _)| moves)
+        .unwrap_or_else(|e| {
+            tracing::error!(
+                "Failed to read the move list {}: {}",
+                config.move_list_path.display(),
+                e
             );
-
-            let mut response = reqwest::blocking::get(&submission.attachment.url)
-                .context("Failed to get attachment")?;
-            zip.start_file(&new_file_name, options).context(format!(
-                "Failed to start writing attachment {}",
-                submission.attachment.id,
-            ))?;
-            std::io::copy(&mut response, &mut zip).context(format!(
-                "Failed to write attachment {}",
-                submission.attachment.id
-            ))?;
-        }
-
-        zip.finish()
-            .context("Failed to finish writing to archive")?;
-
-        Ok::<ArchiveResult, Error>(ArchiveResult {
-            archive: zip_file_path,
-            message,
-        })
-    })
-    .await
-    .context("Failed to create archive")??;
-
-    if archive_result.message.is_some() {
-        ctx.send(CreateReply {
-            content: archive_result.message,
-            ephemeral: Some(true),
-            ..Default::default()
-        })
-        .await
-        .context("Failed to send message")?;
+            Vec::new()
+        });
+    if move_list.is_empty() {
+        tracing::warn!(
+            "The move list {} is empty, every /pull and /search will reply that it's not configured",
+            config.move_list_path.display()
+        );
     }
 
-    let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
-    let form = reqwest::multipart::Form::new()
-        .text("expires", "1") // download link expires in 1 hour
-        .file("file", archive_result.archive)
-        .await
-        .context("Failed to create upload form")?;
-
-    let response = client
-        .post(FILE_UPLOAD_URL)
-        .multipart(form)
-        .send()
-        .await
-        .context("Failed to send request")?
-        .text()
-        .await
-        .context("Failed to get response text")?;
-
-    let reply = if response.validate_url() {
-        // 0x0.st renames the uploaded file
-        // append zip filename to download url to get correct filename
-        format!(
-            "{}/{}\nLink expires in 1 hour",
-            &response.trim(),
-            zip_file_name
-        )
-    } else {
-        tracing::error!("Failed to create download link. Response:\n{}", response);
-        "Failed to create download link".to_string()
+    let move_aliases = match &config.move_aliases_path {
+        Some(path) => moves::load_aliases(path).unwrap_or_else(|e| {
+            tracing::error!(
+                "Failed to read the move aliases {}: {:#}",
+                path.display(),
+                e
+            );
+            HashMap::new()
+        }),
+        None => HashMap::new(),
     };
 
-    ctx.send(CreateReply {
-        content: Some(reply),
-        ephemeral: Some(true),
-        ..Default::default()
-    })
-    .await
-    .context("Failed to send message")?;
-
-    dir.close()
-        .context("Failed to close and remove temporary directory")?;
-
-    Ok(())
-}
-
-#[poise::command(slash_command)]
-#[instrument(name = "search", skip_all, fields(id = ctx.id(), username = ctx.author().name, search_term = search_term))]
-async fn search(
-    ctx: Context<'_>,
-    #[description = "Search term"] search_term: String,
-) -> Result<(), Error> {
-    let search_term = search_term.to_lowercase();
-
-    let results: Vec<&'static str> = ctx
-        .data()
-        .move_list
-        .iter()
-        .filter(|line| line.contains(&search_term))
-        .cloned()
-        .collect();
-
-    let reply = if results.is_empty() {
-        format!("No move contains {}", search_term)
-    } else {
-        format!(
-            "Moves containing \"{}\":\n{}",
-            search_term,
-            results.join("\n")
-        )
+    let move_threads = match &config.move_threads_path {
+        Some(path) => moves::load_threads(path, &move_list).unwrap_or_else(|e| {
+            tracing::error!(
+                "Failed to read the move threads {}: {:#}",
+                path.display(),
+                e
+            );
+            HashMap::new()
+        }),
+        None => HashMap::new(),
     };
 
-    ctx.send(CreateReply {
-        content: Some(reply),
-        ephemeral: Some(true),
-        ..Default::default()
-    })
-    .await
-    .context("Failed to send message")?;
-
-    Ok(())
-}
-
-#[tokio::main]
-async fn main() {
-    dotenvy::dotenv().ok();
-
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "info,serenity=error".into()),
-        )
-        .with(tracing_subscriber::fmt::layer().with_span_events(FmtSpan::NEW | FmtSpan::CLOSE))
-        .init();
-
-    let token = std::env::var("DISCORD_TOKEN").expect("DISCORD_TOKEN not set");
-    let intents =
-        serenity::GatewayIntents::non_privileged() | serenity::GatewayIntents::MESSAGE_CONTENT;
+    let clip_registry = ClipRegistry::load(config.clip_registry_path.clone())
+        .expect("Failed to load the clip registry");
 
-    let move_list: Vec<&'static str> = include_str!("../move-list.txt").lines().collect();
+    let background_pulls = Arc::new(BackgroundPulls::default());
+    let shared_background_pulls = background_pulls.clone();
 
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
-            commands: vec![pull(), search()],
+            commands: commands::all(),
+            on_error: |error| Box::pin(error::on_error(error)),
+            pre_command: |ctx| {
+                Box::pin(async move {
+                    // remembered so reports can include what the user ran before
+                    if ctx.command().name != "report" {
+                        ctx.data()
+                            .last_commands
+                            .lock()
+                            .unwrap()
+                            .insert(ctx.author().id, ctx.invocation_string());
+                    }
+
+                    let audit_channel_id = ctx.data().config.load().audit_channel_id;
+                    if let Some(channel_id) = audit_channel_id {
+                        audit::record(
+                            ctx.serenity_context().http.clone(),
+                            channel_id,
+                            ctx.author(),
+                            ctx.invocation_string(),
+                        );
+                    }
+                })
+            },
             ..Default::default()
         })
-        .setup(|ctx, _ready, framework| {
+        .setup(move |ctx, _ready, framework| {
             Box::pin(async move {
-                poise::builtins::register_globally(ctx, &framework.options().commands).await?;
-                Ok(Data { move_list })
+                let commands =
+                    poise::builtins::create_application_commands(&framework.options().commands);
+                match serenity::Command::set_global_commands(ctx, commands).await {
+                    Ok(registered) => tracing::info!(
+                        "Registered {} commands: {}",
+                        registered.len(),
+                        registered
+                            .iter()
+                            .map(|command| command.name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    Err(e) => {
+                        tracing::error!(
+                            "Failed to register commands, they won't show up in Discord: {}",
+                            e
+                        );
+                        if config.exit_on_registration_failure {
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                let housekeeping_interval = config.housekeeping_interval;
+                let watched_move_list = config
+                    .watch_move_list
+                    .then(|| config.move_list_path.clone());
+                let data = Data::new(
+                    move_list,
+                    move_aliases,
+                    config,
+                    uploader,
+                    clip_registry,
+                    move_threads,
+                    shared_background_pulls,
+                );
+                housekeeping::spawn(housekeeping_interval, data.expiring());
+                if let Some(path) = watched_move_list
+                    && let Err(e) = watcher::spawn(path, data.move_list.clone())
+                {
+                    tracing::error!("Failed to watch the move list, it won't reload: {:#}", e);
+                }
+                Ok(data)
             })
         })
         .build();
@@ -306,5 +215,54 @@ async fn main() {
         .framework(framework)
         .await;
 
-    client.unwrap().start().await.unwrap();
+    let mut client = client.unwrap();
+    let shard_manager = client.shard_manager.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        tracing::info!("Shutting down, waiting for background pulls to finish");
+        let unfinished = background_pulls.drain(SHUTDOWN_TIMEOUT).await;
+        if !unfinished.is_empty() {
+            tracing::warn!(
+                "Shutting down with {} background pulls unfinished: {}",
+                unfinished.len(),
+                unfinished.join(", ")
+            );
+        }
+        shard_manager.shutdown_all().await;
+    });
+
+    client.start().await.unwrap();
+}
+
+/// Resolves on Ctrl+C, or on SIGTERM as sent by `docker stop` and systemd
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .expect("Failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+fn new_uploader(backend: UploadBackend, url: &str, config: &Config) -> Box<dyn Uploader> {
+    match backend {
+        UploadBackend::ZeroXZero => Box::new(
+            ZeroXZero::new(
+                url.to_owned(),
+                &config.upload_user_agent,
+                config.append_file_name,
+            )
+            .expect("Failed to create the uploader"),
+        ),
+        UploadBackend::Tus => Box::new(
+            Tus::new(url.to_owned(), &config.upload_user_agent)
+                .expect("Failed to create the uploader"),
+        ),
+    }
 }
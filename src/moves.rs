@@ -0,0 +1,305 @@
+use anyhow::Context as AnyhowContext;
+use poise::serenity_prelude::ChannelId;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Longest move name Discord accepts as a command option value
+const MAX_MOVE_NAME_LENGTH: usize = 100;
+
+/// Reply when there are no moves to look up, so a config problem isn't mistaken for a typo
+pub const MOVE_LIST_NOT_CONFIGURED: &str =
+    "Move list not configured, please ask an admin to check it with `/validate_moves`";
+
+/// Move list compiled into the bot, used when the move list file doesn't exist
+const BUILTIN_MOVE_LIST: &str = include_str!("../move-list.txt");
+
+/// Move names in the move list file at `path`, skipping blank lines
+pub fn load(path: &Path) -> std::io::Result<Vec<String>> {
+    Ok(parse(&std::fs::read_to_string(path)?))
+}
+
+/// Where [`load_or_builtin`] got the move list from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveListSource {
+    File,
+    Builtin,
+}
+
+/// Move names in the move list file at `path`, or the compiled-in move list if there is no
+/// file there. Logs which of the two was loaded
+pub fn load_or_builtin(path: &Path) -> std::io::Result<(Vec<String>, MoveListSource)> {
+    match load(path) {
+        Ok(moves) => {
+            tracing::info!("Loaded {} moves from {}", moves.len(), path.display());
+            Ok((moves, MoveListSource::File))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let moves = parse(BUILTIN_MOVE_LIST);
+            tracing::info!(
+                "{} doesn't exist, loaded the {} moves compiled into the bot",
+                path.display(),
+                moves.len()
+            );
+            Ok((moves, MoveListSource::Builtin))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Move names of a move list, one per line. Files edited on Windows can end lines with
+/// `\r\n` or start with a byte order mark, neither ends up in a move name
+fn parse(source: &str) -> Vec<String> {
+    lines(source)
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Lines of a move list without the byte order mark, split at `\n`, `\r\n` and lone `\r`
+fn lines(source: &str) -> Vec<String> {
+    let source = source.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let source = source.strip_suffix(['\n', '\r']).unwrap_or(&source);
+    if source.is_empty() {
+        return Vec::new();
+    }
+    source.split(['\n', '\r']).map(str::to_owned).collect()
+}
+
+/// Aliases of each move in the alias file at `path`, lines of `alias=move_name`.
+/// Blank lines and lines starting with `#` are skipped, aliases are lowercased
+pub fn load_aliases(path: &Path) -> anyhow::Result<HashMap<String, Vec<String>>> {
+    let source = std::fs::read_to_string(path)?;
+
+    let mut aliases: HashMap<String, Vec<String>> = HashMap::new();
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (alias, move_name) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Line {}: expected alias=move_name", index + 1))?;
+        aliases
+            .entry(move_name.trim().to_owned())
+            .or_default()
+            .push(alias.trim().to_lowercase());
+    }
+    Ok(aliases)
+}
+
+/// Thread of each move in the JSON file at `path`, an object of `"move_name": thread_id`.
+/// Moves that aren't in `move_list` are left out with a warning
+pub fn load_threads(
+    path: &Path,
+    move_list: &[String],
+) -> anyhow::Result<HashMap<String, ChannelId>> {
+    let source = std::fs::read_to_string(path)?;
+    let threads: HashMap<String, u64> = serde_json::from_str(&source)
+        .context("Expected an object of \"move_name\": thread_id entries")?;
+
+    let mut known = HashMap::new();
+    for (move_name, thread_id) in threads {
+        if thread_id == 0 {
+            tracing::warn!(
+                "Thread of {} in {} is 0, leaving it out",
+                move_name,
+                path.display()
+            );
+        } else if let Some(known_name) = resolve(move_list, &move_name) {
+            known.insert(known_name.to_owned(), ChannelId::new(thread_id));
+        } else {
+            tracing::warn!(
+                "{} in {} isn't in the move list, leaving it out",
+                move_name,
+                path.display()
+            );
+        }
+    }
+    Ok(known)
+}
+
+/// Move names are formatted as `<chapter>-<category>-<page>-<title>`
+pub fn move_title(move_name: &str) -> &str {
+    move_name.splitn(4, '-').nth(3).unwrap_or(move_name)
+}
+
+/// Entry of the move list matching `move_name` regardless of case, with the list's casing
+pub fn resolve<'a>(move_list: &'a [String], move_name: &str) -> Option<&'a str> {
+    let move_name = move_name.trim();
+    move_list
+        .iter()
+        .find(|entry| entry.eq_ignore_ascii_case(move_name))
+        .map(String::as_str)
+}
+
+/// Readable category of a move, e.g. `False Shuffles` for `02-false_shuffles-0107-...`
+pub fn move_category(move_name: &str) -> Option<String> {
+    let mut parts = move_name.splitn(4, '-');
+    let category = parts.nth(1)?;
+    parts.nth(1)?;
+
+    Some(
+        category
+            .split('_')
+            .filter(|word| !word.is_empty())
+            .map(|word| {
+                let mut chars = word.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// Page of a move, e.g. 107 for `02-false_shuffles-0107-...`
+pub fn move_page(move_name: &str) -> Option<u32> {
+    move_name.split('-').nth(2)?.parse().ok()
+}
+
+/// Move a thread is about, threads are named after the move name
+/// or its title with the page, e.g. `Conley Three-Riffle Variation (Page 107)`
+pub fn thread_move<'a>(move_list: &'a [String], thread_name: &str) -> Option<&'a str> {
+    if let Some(move_name) = resolve(move_list, thread_name) {
+        return Some(move_name);
+    }
+
+    let lowercase = thread_name.to_lowercase();
+    let (title, after) = lowercase.rsplit_once("page")?;
+    let page: u32 = after
+        .trim_start()
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>()
+        .parse()
+        .ok()?;
+
+    // several moves can share a page, the title tells them apart
+    let normalize =
+        |title: &str| -> String { title.chars().filter(char::is_ascii_alphanumeric).collect() };
+    let title = normalize(title);
+    move_list
+        .iter()
+        .filter(|move_name| move_page(move_name) == Some(page))
+        .min_by_key(|move_name| strsim::levenshtein(&title, &normalize(move_title(move_name))))
+        .map(String::as_str)
+}
+
+/// Up to `count` moves closest to `query` by edit distance, to either the full name or the title
+pub fn closest_matches<'a>(move_list: &'a [String], query: &str, count: usize) -> Vec<&'a str> {
+    scored_matches(move_list, query)
+        .into_iter()
+        .take(count)
+        .map(|(_, move_name)| move_name)
+        .collect()
+}
+
+/// Every move with its edit distance to `query`, the lower of the distances to its name and
+/// title, closest first
+pub fn scored_matches<'a>(move_list: &'a [String], query: &str) -> Vec<(usize, &'a str)> {
+    let query = query.trim().to_lowercase();
+
+    let mut scored: Vec<(usize, &'a str)> = move_list
+        .iter()
+        .map(|move_name| {
+            let move_name = move_name.as_str();
+            let distance = strsim::levenshtein(&query, move_name)
+                .min(strsim::levenshtein(&query, move_title(move_name)));
+            (distance, move_name)
+        })
+        .collect();
+    scored.sort_by_key(|&(distance, _)| distance);
+    scored
+}
+
+/// Problems in a move list file: blank lines, duplicates, surrounding whitespace and long lines.
+/// Lines are split like the loader splits them
+pub fn validate(source: &str) -> Vec<String> {
+    let mut issues = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for (index, line) in lines(source).iter().enumerate() {
+        let line_number = index + 1;
+
+        if line.trim().is_empty() {
+            issues.push(format!("Line {}: blank line", line_number));
+            continue;
+        }
+        if line.trim() != line {
+            issues.push(format!(
+                "Line {}: leading or trailing whitespace",
+                line_number
+            ));
+        }
+        if line.chars().count() > MAX_MOVE_NAME_LENGTH {
+            issues.push(format!(
+                "Line {}: longer than {} characters",
+                line_number, MAX_MOVE_NAME_LENGTH
+            ));
+        }
+        if let Some(first) = seen.insert(line.trim().to_lowercase(), line_number) {
+            issues.push(format!("Line {}: duplicate of line {}", line_number, first));
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn missing_file_falls_back_to_builtin_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let (move_list, source) = load_or_builtin(&dir.path().join("move-list.txt")).unwrap();
+        assert_eq!(source, MoveListSource::Builtin);
+        assert!(!move_list.is_empty());
+        assert_eq!(move_list, parse(BUILTIN_MOVE_LIST));
+    }
+
+    #[test]
+    fn load_strips_crlf_line_endings() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(
+            b"\xef\xbb\xbf02-false_shuffles-0107-conleys_three_riffle_variation\r\n\r\n05-false_deals-0409-2nd_deal\r\n",
+        )
+        .unwrap();
+
+        let move_list = load(file.path()).unwrap();
+        assert_eq!(
+            move_list,
+            [
+                "02-false_shuffles-0107-conleys_three_riffle_variation",
+                "05-false_deals-0409-2nd_deal"
+            ]
+        );
+        assert_eq!(
+            resolve(&move_list, "05-false_deals-0409-2nd_deal"),
+            Some("05-false_deals-0409-2nd_deal")
+        );
+    }
+
+    #[test]
+    fn parse_handles_every_line_ending() {
+        assert_eq!(parse("a\nb"), ["a", "b"]);
+        assert_eq!(parse("a\r\nb\r\n"), ["a", "b"]);
+        assert_eq!(parse("a\rb\r"), ["a", "b"]);
+        assert!(parse("\r\n\n").is_empty());
+    }
+
+    #[test]
+    fn validate_splits_lines_like_the_loader() {
+        assert!(validate("\u{feff}a\r\nb\r\n").is_empty());
+        assert!(validate("a\rb\r").is_empty());
+        assert_eq!(
+            validate("\u{feff}a\rA\r\n\nb"),
+            ["Line 2: duplicate of line 1", "Line 3: blank line"]
+        );
+    }
+}
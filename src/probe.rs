@@ -0,0 +1,36 @@
+use crate::process;
+use anyhow::Context as AnyhowContext;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// ffprobe only reads the header, a run this long is stuck on a malformed clip
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Length of `video` in seconds, `None` if ffprobe isn't installed
+pub fn duration(video: &Path) -> anyhow::Result<Option<f64>> {
+    let output = match process::output_with_timeout(
+        Command::new("ffprobe")
+            .args(["-v", "error", "-show_entries", "format=duration"])
+            .args(["-of", "default=noprint_wrappers=1:nokey=1"])
+            .arg(video)
+            .stdin(Stdio::null()),
+        PROBE_TIMEOUT,
+    ) {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).context("Failed to run ffprobe"),
+    };
+
+    anyhow::ensure!(
+        output.status.success(),
+        "ffprobe failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map(Some)
+        .context("ffprobe returned no duration")
+}
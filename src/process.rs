@@ -0,0 +1,76 @@
+use std::io::{self, Read};
+use std::process::{Child, Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+/// How often a running child is checked for having exited
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Runs `command` like [`Command::output`], killing the child if it runs longer than `timeout`.
+/// A killed child fails with [`io::ErrorKind::TimedOut`]
+pub fn output_with_timeout(command: &mut Command, timeout: Duration) -> io::Result<Output> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // the pipes are drained while waiting, a child filling one would block otherwise
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            kill(&mut child);
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("killed after {} seconds", timeout.as_secs()),
+            ));
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+fn kill(child: &mut Child) {
+    if let Err(e) = child.kill() {
+        tracing::warn!("Failed to kill child process {}: {}", child.id(), e);
+    }
+    // reaps the child so it doesn't linger as a zombie
+    let _ = child.wait();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kills_child_after_timeout() {
+        let started = Instant::now();
+        let error = output_with_timeout(
+            Command::new("sleep").arg("10").stdin(Stdio::null()),
+            Duration::from_millis(200),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
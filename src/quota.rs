@@ -0,0 +1,61 @@
+use crate::housekeeping::Expiring;
+use poise::serenity_prelude::{GuildId, Timestamp};
+use std::collections::HashMap;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Bytes each guild uploaded today, reset at midnight UTC
+#[derive(Default)]
+pub struct UploadQuota {
+    day: i64,
+    used: HashMap<GuildId, u64>,
+}
+
+impl UploadQuota {
+    /// Reserves `bytes` of the guild's `limit` for an upload, returns the bytes left if they don't fit
+    pub fn try_reserve(&mut self, guild_id: GuildId, bytes: u64, limit: u64) -> Result<(), u64> {
+        self.roll_over();
+        let used = self.used.entry(guild_id).or_default();
+        if *used + bytes > limit {
+            return Err(limit.saturating_sub(*used));
+        }
+        *used += bytes;
+        Ok(())
+    }
+
+    /// Gives back a reservation whose upload failed
+    pub fn release(&mut self, guild_id: GuildId, bytes: u64) {
+        self.roll_over();
+        if let Some(used) = self.used.get_mut(&guild_id) {
+            *used = used.saturating_sub(bytes);
+        }
+    }
+
+    /// Unix timestamp of the next reset
+    pub fn resets_at() -> i64 {
+        (today() + 1) * SECONDS_PER_DAY
+    }
+
+    fn roll_over(&mut self) {
+        let today = today();
+        if self.day != today {
+            self.day = today;
+            self.used.clear();
+        }
+    }
+}
+
+impl Expiring for UploadQuota {
+    /// Drops yesterday's usage once the day is over, even without uploads today
+    fn prune(&mut self) -> usize {
+        let before = self.used.len();
+        self.roll_over();
+        before - self.used.len()
+    }
+}
+
+fn today() -> i64 {
+    Timestamp::now()
+        .unix_timestamp()
+        .div_euclid(SECONDS_PER_DAY)
+}
@@ -0,0 +1,74 @@
+use anyhow::Context as AnyhowContext;
+use poise::serenity_prelude::AttachmentId;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Clips already archived per move, kept in a JSON file so later pulls can skip them after restarts
+pub struct ClipRegistry {
+    path: PathBuf,
+    clips: HashMap<String, HashSet<AttachmentId>>,
+}
+
+impl ClipRegistry {
+    /// Loads the registry at `path`, empty if the file doesn't exist yet
+    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let clips = match std::fs::read_to_string(&path) {
+            Ok(source) => serde_json::from_str::<HashMap<String, Vec<u64>>>(&source)
+                .context(format!("Invalid clip registry {}", path.display()))?
+                .into_iter()
+                .map(|(move_name, ids)| {
+                    let ids = ids.into_iter().map(AttachmentId::new).collect();
+                    (move_name, ids)
+                })
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                return Err(e).context(format!("Failed to read clip registry {}", path.display()));
+            }
+        };
+        Ok(Self { path, clips })
+    }
+
+    /// Clips of `move_name` in earlier archives
+    pub fn archived(&self, move_name: &str) -> HashSet<AttachmentId> {
+        self.clips.get(move_name).cloned().unwrap_or_default()
+    }
+
+    /// Adds the clips of an archive of `move_name` and saves the registry
+    pub fn record(
+        &mut self,
+        move_name: &str,
+        attachment_ids: impl IntoIterator<Item = AttachmentId>,
+    ) -> anyhow::Result<()> {
+        self.clips
+            .entry(move_name.to_owned())
+            .or_default()
+            .extend(attachment_ids);
+        self.save()
+    }
+
+    /// Replaces the file in one step, a crash while writing leaves the previous registry intact
+    fn save(&self) -> anyhow::Result<()> {
+        let clips: HashMap<&str, Vec<u64>> = self
+            .clips
+            .iter()
+            .map(|(move_name, ids)| (move_name.as_str(), ids.iter().map(|id| id.get()).collect()))
+            .collect();
+        let json = serde_json::to_string(&clips).context("Failed to serialize clip registry")?;
+
+        let dir = self
+            .path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let mut file = tempfile::NamedTempFile::new_in(dir)
+            .context("Failed to create temporary clip registry")?;
+        std::io::Write::write_all(&mut file, json.as_bytes())
+            .context("Failed to write clip registry")?;
+        file.persist(&self.path).context(format!(
+            "Failed to replace clip registry {}",
+            self.path.display()
+        ))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+use crate::data::{Context, Error};
+use anyhow::Context as AnyhowContext;
+use poise::CreateReply;
+use poise::serenity_prelude::{
+    ComponentInteractionCollector, CreateActionRow, CreateAttachment, CreateButton, CreateEmbed,
+    CreateInteractionResponse, CreateMessage, Mentionable, Timestamp,
+};
+use std::time::Duration;
+
+/// Longest message Discord accepts
+const MAX_MESSAGE_LENGTH: usize = 2000;
+/// Age of an interaction after which replies are sent as messages, its token expires at 15 minutes
+const INTERACTION_REPLY_DEADLINE_SECONDS: i64 = 14 * 60;
+const TRUNCATED_NOTE: &str = "\n… (truncated)";
+
+/// Invocation data of commands whose later replies are sent as messages, see [`reply_directly`]
+struct DirectReplies;
+
+/// Sends the rest of the command's replies as direct messages, or channel messages if they're
+/// public, for commands that already replied that they keep working in the background
+pub async fn reply_directly(ctx: Context<'_>) {
+    ctx.set_invocation_data(DirectReplies).await;
+}
+
+/// Sends an ephemeral text reply, cut off with a note if it doesn't fit in one message
+pub async fn send_text(ctx: Context<'_>, content: impl Into<String>) -> Result<(), Error> {
+    send(
+        ctx,
+        CreateReply::default().content(truncate(content.into())),
+    )
+    .await
+}
+
+/// Sends a text reply everyone in the channel can see, cut off like [`send_text`]
+pub async fn send_public_text(ctx: Context<'_>, content: impl Into<String>) -> Result<(), Error> {
+    deliver(
+        ctx,
+        CreateReply::default().content(truncate(content.into())),
+    )
+    .await
+}
+
+/// Sends an embed reply, ephemeral unless `public`
+pub async fn send_embed(ctx: Context<'_>, embed: CreateEmbed, public: bool) -> Result<(), Error> {
+    let reply = CreateReply::default().embed(embed);
+    if public {
+        deliver(ctx, reply).await
+    } else {
+        send(ctx, reply).await
+    }
+}
+
+/// Sends an ephemeral text reply, attached as `file_name` instead if it doesn't fit in one message
+pub async fn send_text_or_file(
+    ctx: Context<'_>,
+    content: impl Into<String>,
+    file_name: &str,
+) -> Result<(), Error> {
+    let content = content.into();
+    if content.chars().count() <= MAX_MESSAGE_LENGTH {
+        return send(ctx, CreateReply::default().content(content)).await;
+    }
+
+    let summary = format!(
+        "{}\nToo long for a message, see the attached file",
+        content.lines().next().unwrap_or_default()
+    );
+    send_file(ctx, summary, content.into_bytes(), file_name).await
+}
+
+/// Sends an ephemeral reply with `file` attached as `file_name`
+pub async fn send_file(
+    ctx: Context<'_>,
+    content: impl Into<String>,
+    file: Vec<u8>,
+    file_name: &str,
+) -> Result<(), Error> {
+    send_files(ctx, content, vec![(file, file_name.to_owned())]).await
+}
+
+/// Sends an ephemeral reply with each file attached under its name
+pub async fn send_files(
+    ctx: Context<'_>,
+    content: impl Into<String>,
+    files: Vec<(Vec<u8>, String)>,
+) -> Result<(), Error> {
+    let reply = files.into_iter().fold(
+        CreateReply::default().content(truncate(content.into())),
+        |reply, (file, file_name)| reply.attachment(CreateAttachment::bytes(file, file_name)),
+    );
+    send(ctx, reply).await
+}
+
+/// Sends an ephemeral text reply with a button labeled `label`,
+/// returns whether the user pressed it within `timeout`
+pub async fn ask(
+    ctx: Context<'_>,
+    content: &str,
+    label: &str,
+    custom_id: String,
+    timeout: Duration,
+) -> Result<bool, Error> {
+    let button = CreateButton::new(&custom_id).label(label);
+    let handle = ctx
+        .send(
+            CreateReply::default()
+                .content(content)
+                .components(vec![CreateActionRow::Buttons(vec![button])])
+                .ephemeral(true),
+        )
+        .await
+        .context("Failed to send message")?;
+
+    let pressed = ComponentInteractionCollector::new(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .custom_ids(vec![custom_id])
+        .timeout(timeout)
+        .await;
+
+    // removed either way, so the button can't be pressed twice
+    let removed = handle
+        .edit(
+            ctx,
+            CreateReply::default()
+                .content(content)
+                .components(Vec::new()),
+        )
+        .await;
+    if let Err(e) = removed {
+        tracing::warn!(
+            "Failed to remove the button of interaction {}: {}",
+            ctx.id(),
+            e
+        );
+    }
+
+    let Some(interaction) = pressed else {
+        return Ok(false);
+    };
+    interaction
+        .create_response(ctx, CreateInteractionResponse::Acknowledge)
+        .await
+        .context("Failed to acknowledge button")?;
+    Ok(true)
+}
+
+async fn send(ctx: Context<'_>, reply: CreateReply) -> Result<(), Error> {
+    deliver(ctx, reply.ephemeral(true)).await
+}
+
+/// Sends `reply` as the interaction response. After [`reply_directly`], or once the interaction
+/// token is about to expire on a very long pull, it's sent as a direct message if it's ephemeral
+/// and a message in the channel otherwise, also when the user doesn't take direct messages
+async fn deliver(ctx: Context<'_>, reply: CreateReply) -> Result<(), Error> {
+    let direct = ctx.invocation_data::<DirectReplies>().await.is_some();
+    let age = Timestamp::now().unix_timestamp() - ctx.created_at().unix_timestamp();
+    if !direct && age < INTERACTION_REPLY_DEADLINE_SECONDS {
+        ctx.send(reply).await.context("Failed to send message")?;
+        return Ok(());
+    }
+
+    let ephemeral = reply.ephemeral.unwrap_or(false);
+    if !direct {
+        tracing::warn!(
+            "Interaction {} is {}s old and its token is about to expire, replying with a {} instead",
+            ctx.id(),
+            age,
+            if ephemeral {
+                "direct message"
+            } else {
+                "channel message"
+            }
+        );
+    }
+
+    let content = reply.content.unwrap_or_default();
+    let message = CreateMessage::new()
+        .embeds(reply.embeds)
+        .add_files(reply.attachments);
+    if ephemeral {
+        let sent = ctx
+            .author()
+            .direct_message(ctx, message.clone().content(content.clone()))
+            .await;
+        match sent {
+            Ok(_) => return Ok(()),
+            // closed direct messages are the usual reason
+            Err(e) => tracing::warn!(
+                "Failed to send {} a direct message, replying in the channel instead: {}",
+                ctx.author().name,
+                e
+            ),
+        }
+    }
+
+    let content = truncate(format!("{} {}", ctx.author().mention(), content));
+    ctx.channel_id()
+        .send_message(ctx, message.content(content))
+        .await
+        .context("Failed to send message")?;
+    Ok(())
+}
+
+fn truncate(content: String) -> String {
+    if content.chars().count() <= MAX_MESSAGE_LENGTH {
+        return content;
+    }
+
+    let mut truncated: String = content
+        .chars()
+        .take(MAX_MESSAGE_LENGTH - TRUNCATED_NOTE.chars().count())
+        .collect();
+    truncated.push_str(TRUNCATED_NOTE);
+    truncated
+}
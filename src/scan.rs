@@ -0,0 +1,212 @@
+use anyhow::Context as AnyhowContext;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, GetMessages, GuildChannel, Message, MessageId,
+};
+use std::cmp::Reverse;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+const PAGE_SIZE: u8 = 100;
+/// Pages each segment fetches ahead of the ones being processed
+const PREFETCH_PAGES: usize = 10;
+/// Archived forum posts listed per request, the most Discord allows
+const ARCHIVED_THREADS_LIMIT: u64 = 100;
+/// Pages of archived forum posts listed at most, so a runaway cursor can't loop forever
+const MAX_ARCHIVED_THREAD_PAGES: usize = 100;
+
+type Page = serenity::Result<Vec<Message>>;
+
+/// Pages of a thread's messages, newest first.
+///
+/// Pagination is cursor based, so the id range is split into segments that are paged through
+/// in parallel and handed out in order. Each segment keeps fetching while earlier pages are
+/// processed, serenity's rate limiter keeps the requests under Discord's limits.
+pub struct MessagePages {
+    segments: VecDeque<(JoinHandle<()>, mpsc::Receiver<Page>)>,
+}
+
+impl MessagePages {
+    /// Scans the messages of `channel_id` from `start` to `end` inclusive in `concurrency` segments,
+    /// the whole thread is scanned in one segment if its last message is unknown
+    pub fn new(
+        http: Arc<serenity::Http>,
+        channel_id: ChannelId,
+        start: MessageId,
+        end: Option<MessageId>,
+        concurrency: usize,
+    ) -> Self {
+        let segments = segments(start, end, concurrency)
+            .into_iter()
+            .map(|(before, lower)| {
+                let (sender, receiver) = mpsc::channel(PREFETCH_PAGES);
+                let task = tokio::spawn(page_through(
+                    http.clone(),
+                    channel_id,
+                    before,
+                    lower,
+                    sender,
+                ));
+                (task, receiver)
+            })
+            .collect();
+        Self { segments }
+    }
+
+    /// Next page of messages, `None` once the whole range was scanned
+    pub async fn next(&mut self) -> anyhow::Result<Option<Vec<Message>>> {
+        while let Some((_, receiver)) = self.segments.front_mut() {
+            match receiver.recv().await {
+                Some(page) => return page.context("Failed to retrieve messages").map(Some),
+                None => {
+                    self.segments.pop_front();
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Drop for MessagePages {
+    fn drop(&mut self) {
+        // stops fetching segments nobody is going to read when the scan ends early
+        for (task, _) in &self.segments {
+            task.abort();
+        }
+    }
+}
+
+/// Open and archived posts of `forum`, newest first
+pub async fn forum_threads(
+    http: &serenity::Http,
+    forum: &GuildChannel,
+) -> anyhow::Result<Vec<GuildChannel>> {
+    let active = forum
+        .guild_id
+        .get_active_threads(http)
+        .await
+        .context("Failed to get active threads")?;
+    let archived = archived_threads(http, forum.id).await?;
+
+    let mut seen = HashSet::new();
+    let mut threads: Vec<GuildChannel> = active
+        .threads
+        .into_iter()
+        .filter(|thread| thread.parent_id == Some(forum.id))
+        .chain(archived)
+        .filter(|thread| seen.insert(thread.id))
+        .collect();
+    threads.sort_by_key(|thread| Reverse(thread.id));
+    Ok(threads)
+}
+
+/// Archived public threads of `channel_id`, most recently archived first
+async fn archived_threads(
+    http: &serenity::Http,
+    channel_id: ChannelId,
+) -> anyhow::Result<Vec<GuildChannel>> {
+    let mut threads = Vec::new();
+    let mut before: Option<serenity::Timestamp> = None;
+    for _ in 0..MAX_ARCHIVED_THREAD_PAGES {
+        // serenity sends the `before` cursor as an id while Discord wants a timestamp,
+        // so the request is made directly
+        let mut params = vec![("limit", ARCHIVED_THREADS_LIMIT.to_string())];
+        if let Some(before) = before {
+            params.push(("before", before.to_string()));
+        }
+        let page: serenity::ThreadsData = http
+            .fire(
+                serenity::Request::new(
+                    serenity::Route::ChannelArchivedPublicThreads { channel_id },
+                    serenity::LightMethod::Get,
+                )
+                .params(Some(params)),
+            )
+            .await
+            .context("Failed to get archived threads")?;
+
+        let oldest = page
+            .threads
+            .iter()
+            .filter_map(|thread| thread.thread_metadata?.archive_timestamp)
+            .min();
+        threads.extend(page.threads);
+        match oldest {
+            Some(oldest) if page.has_more && before.is_none_or(|before| oldest < before) => {
+                before = Some(oldest);
+            }
+            _ => return Ok(threads),
+        }
+    }
+
+    tracing::warn!(
+        "Forum {} has more than {} archived posts, older ones are not scanned",
+        channel_id,
+        MAX_ARCHIVED_THREAD_PAGES as u64 * ARCHIVED_THREADS_LIMIT
+    );
+    Ok(threads)
+}
+
+/// Splits `start..=end` into `count` segments of equal id ranges, newest first,
+/// as pairs of the exclusive `before` cursor to start from and the lowest id to keep
+fn segments(
+    start: MessageId,
+    end: Option<MessageId>,
+    count: usize,
+) -> Vec<(Option<MessageId>, MessageId)> {
+    let Some(end) = end.filter(|end| end > &start && count > 1) else {
+        return vec![(None, start)];
+    };
+
+    // snowflakes start with their timestamp, equal id ranges are equal time spans
+    let step = (end.get() - start.get()) / count as u64;
+    if step == 0 {
+        return vec![(None, start)];
+    }
+
+    (0..count as u64)
+        .rev()
+        .map(|k| {
+            let before =
+                (k + 1 < count as u64).then(|| MessageId::new(start.get() + step * (k + 1)));
+            (before, MessageId::new(start.get() + step * k))
+        })
+        .collect()
+}
+
+/// Sends pages of messages before `before` down to `lower` until the segment is done
+/// or the receiver is gone
+async fn page_through(
+    http: Arc<serenity::Http>,
+    channel_id: ChannelId,
+    mut before: Option<MessageId>,
+    lower: MessageId,
+    pages: mpsc::Sender<Page>,
+) {
+    loop {
+        let mut builder = GetMessages::new().limit(PAGE_SIZE);
+        if let Some(id) = before {
+            builder = builder.before(id);
+        }
+
+        let mut messages = match channel_id.messages(&http, builder).await {
+            Ok(messages) => messages,
+            Err(e) => {
+                let _ = pages.send(Err(e)).await;
+                return;
+            }
+        };
+
+        before = messages.last().map(|m| m.id);
+        let done = before.is_none_or(|id| id <= lower);
+        messages.retain(|m| m.id >= lower);
+
+        if !messages.is_empty() && pages.send(Ok(messages)).await.is_err() {
+            return;
+        }
+        if done {
+            return;
+        }
+    }
+}
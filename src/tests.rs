@@ -0,0 +1,217 @@
+//! End-to-end tests of the pull pipeline with the network replaced by mocks
+
+use crate::archive::{self, ArchiveOptions, AttachmentSource, Compression, Submission, TaskError};
+use crate::upload::{self, UploadResponse, Uploader};
+use async_trait::async_trait;
+use poise::serenity_prelude::{Attachment, ChannelId, MessageId};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Serves attachment contents from memory, keyed by url
+#[derive(Default)]
+struct MockSource {
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl AttachmentSource for MockSource {
+    fn fetch(&self, attachment: &Attachment) -> anyhow::Result<Box<dyn Read + '_>> {
+        let file = self
+            .files
+            .get(&attachment.url)
+            .ok_or_else(|| anyhow::anyhow!("No attachment at {}", attachment.url))?;
+        Ok(Box::new(Cursor::new(file.as_slice())))
+    }
+}
+
+/// Records uploaded archives and answers like 0x0.st
+#[derive(Default)]
+struct MockUploader {
+    uploads: Mutex<Vec<PathBuf>>,
+}
+
+#[async_trait]
+impl Uploader for MockUploader {
+    async fn upload(
+        &self,
+        archive: PathBuf,
+        _expires_hours: u64,
+    ) -> anyhow::Result<UploadResponse> {
+        self.uploads.lock().unwrap().push(archive);
+        Ok(UploadResponse {
+            body: "https://0x0.example/abcd.zip\n".to_owned(),
+            token: Some("token".to_owned()),
+            mirrors: Vec::new(),
+        })
+    }
+
+    async fn delete(&self, _url: &str, _token: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn download_link(&self, url: &str, file_name: &str) -> String {
+        format!("{}/{}", url, file_name)
+    }
+}
+
+impl MockSource {
+    /// Adds a video attachment served with `contents`, declaring `size` bytes
+    fn submission(
+        &mut self,
+        id: u64,
+        username: &str,
+        text: &str,
+        contents: &[u8],
+        size: usize,
+    ) -> Submission {
+        let url = format!("https://cdn.example/{}.mp4", id);
+        self.files.insert(url.clone(), contents.to_owned());
+
+        let attachment: Attachment = serde_json::from_value(serde_json::json!({
+            "id": id.to_string(),
+            "filename": format!("clip{}.mp4", id),
+            "proxy_url": url,
+            "size": size,
+            "url": url,
+            "content_type": "video/mp4",
+        }))
+        .unwrap();
+
+        Submission {
+            attachment,
+            channel_id: ChannelId::new(1),
+            message_id: MessageId::new(id),
+            folder: None,
+            move_name: None,
+            username: username.to_owned(),
+            text: text.to_owned(),
+        }
+    }
+}
+
+fn archive_contents(path: &std::path::Path) -> HashMap<String, Vec<u8>> {
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(path).unwrap()).unwrap();
+    (0..zip.len())
+        .map(|i| {
+            let mut file = zip.by_index(i).unwrap();
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).unwrap();
+            (file.name().to_owned(), contents)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn pull_archives_submissions_and_returns_link() {
+    let mut source = MockSource::default();
+    let submissions = vec![
+        source.submission(1, "alice", "first rep", b"alice clip", 10),
+        source.submission(2, "bob", "", b"bob clip", 8),
+        source.submission(3, "carol", "", b"truncated", 100),
+    ];
+
+    let dir = tempfile::tempdir().unwrap();
+    let result = archive::create_archive(
+        &source,
+        dir.path().join("move.zip"),
+        "move",
+        submissions,
+        Vec::new(),
+        None,
+        ArchiveOptions {
+            include_text: true,
+            thumbnails: false,
+            max_file_size: None,
+            oldest_first: false,
+            numbered: false,
+            preserve_names: false,
+            compression: Compression::Deflate,
+            stored_extensions: Vec::new(),
+            transcode: None,
+            duration: None,
+            download_concurrency: NonZeroUsize::new(2).unwrap(),
+            download_delay: Duration::ZERO,
+        },
+    )
+    .unwrap();
+
+    let contents = archive_contents(&result.archive);
+    assert_eq!(contents.len(), 3);
+    assert_eq!(contents["move-alice-1.mp4"], b"alice clip");
+    assert_eq!(contents["move-alice-1.txt"], b"first rep");
+    assert_eq!(contents["move-bob-2.mp4"], b"bob clip");
+    assert_eq!(
+        result.notes,
+        ["clip3.mp4 from carol was skipped because the download was incomplete"]
+    );
+
+    let uploader = MockUploader::default();
+    let response = uploader.upload(result.archive.clone(), 1).await.unwrap();
+    let url = upload::parse_download_url(&response.body).unwrap();
+    assert_eq!(
+        uploader.download_link(&url, "move.zip"),
+        "https://0x0.example/abcd.zip/move.zip"
+    );
+    assert_eq!(*uploader.uploads.lock().unwrap(), [result.archive]);
+}
+
+#[test]
+fn clips_of_several_moves_are_named_after_their_own_move() {
+    let mut source = MockSource::default();
+    let mut submissions = vec![
+        source.submission(1, "alice", "", b"jab clip", 8),
+        source.submission(2, "bob", "", b"dash clip", 9),
+    ];
+    for (submission, move_name) in submissions.iter_mut().zip(["Jab", "Dash"]) {
+        submission.folder = Some(move_name.to_owned());
+        submission.move_name = Some(move_name.to_owned());
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let result = archive::create_archive(
+        &source,
+        dir.path().join("2-moves.zip"),
+        "2 moves",
+        submissions,
+        Vec::new(),
+        None,
+        ArchiveOptions {
+            include_text: false,
+            thumbnails: false,
+            max_file_size: None,
+            oldest_first: false,
+            numbered: false,
+            preserve_names: false,
+            compression: Compression::Deflate,
+            stored_extensions: Vec::new(),
+            transcode: None,
+            duration: None,
+            download_concurrency: NonZeroUsize::new(2).unwrap(),
+            download_delay: Duration::ZERO,
+        },
+    )
+    .unwrap();
+
+    let contents = archive_contents(&result.archive);
+    assert_eq!(contents.len(), 2);
+    assert_eq!(contents["Jab/Jab-alice-1.mp4"], b"jab clip");
+    assert_eq!(contents["Dash/Dash-bob-2.mp4"], b"dash clip");
+}
+
+#[tokio::test]
+async fn panicking_archive_task_removes_temp_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().to_owned();
+
+    let result = archive::run_in_dir(dir, |dir| -> anyhow::Result<()> {
+        std::fs::write(dir.join("move.zip"), b"half an archive").unwrap();
+        panic!("injected panic");
+    })
+    .await;
+
+    assert!(matches!(result, Err(TaskError::Panicked(message)) if message == "injected panic"));
+    assert!(!path.exists());
+}
@@ -0,0 +1,32 @@
+use crate::process;
+use anyhow::Context as AnyhowContext;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Decoding one frame is quick, a run this long is stuck on a malformed clip
+const THUMBNAIL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// First frame of `video` as a JPEG, `None` if ffmpeg isn't installed
+pub fn first_frame(video: &Path) -> anyhow::Result<Option<Vec<u8>>> {
+    let output = match process::output_with_timeout(
+        Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-i"])
+            .arg(video)
+            .args(["-frames:v", "1", "-f", "image2", "-c:v", "mjpeg", "pipe:1"])
+            .stdin(Stdio::null()),
+        THUMBNAIL_TIMEOUT,
+    ) {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).context("Failed to run ffmpeg"),
+    };
+
+    anyhow::ensure!(
+        output.status.success() && !output.stdout.is_empty(),
+        "ffmpeg failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(Some(output.stdout))
+}
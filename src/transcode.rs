@@ -0,0 +1,64 @@
+use crate::process;
+use anyhow::Context as AnyhowContext;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tempfile::NamedTempFile;
+
+/// Longest one clip may take to transcode, ffmpeg is killed after it so a malformed clip can't hang the pull
+const TRANSCODE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How clips are re-encoded to make archives smaller
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TranscodeSettings {
+    /// x264 constant rate factor from 0 to 51, higher is smaller and worse looking
+    pub crf: u8,
+    /// Taller clips are scaled down to this height
+    pub max_height: u32,
+    /// Threads one ffmpeg run may use, so a pull doesn't take every core of the host
+    pub threads: NonZeroUsize,
+    /// Clips of a batch transcoded at the same time
+    pub parallel: NonZeroUsize,
+}
+
+/// Whether ffmpeg is installed
+pub fn ffmpeg_available() -> bool {
+    Command::new("ffmpeg")
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Re-encodes `video` into an H.264 mp4 in a temporary file
+pub fn transcode(video: &Path, settings: TranscodeSettings) -> anyhow::Result<NamedTempFile> {
+    let output = NamedTempFile::new().context("Failed to create transcoded clip file")?;
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-loglevel", "error", "-y", "-i"])
+        .arg(video)
+        .args(["-c:v", "libx264", "-preset", "veryfast", "-crf"])
+        .arg(settings.crf.to_string())
+        // scales down only, -2 keeps the width even as x264 requires
+        .arg("-vf")
+        .arg(format!("scale=-2:'min({},ih)'", settings.max_height))
+        .args(["-c:a", "aac", "-b:a", "128k", "-movflags", "+faststart"])
+        .arg("-threads")
+        .arg(settings.threads.to_string())
+        .args(["-f", "mp4"])
+        .arg(output.path())
+        .stdin(Stdio::null());
+    let result = process::output_with_timeout(&mut command, TRANSCODE_TIMEOUT)
+        .context("Failed to run ffmpeg")?;
+
+    anyhow::ensure!(
+        result.status.success(),
+        "ffmpeg failed: {}",
+        String::from_utf8_lossy(&result.stderr).trim()
+    );
+    Ok(output)
+}
@@ -0,0 +1,333 @@
+use crate::housekeeping::SharedExpiring;
+use anyhow::Context as AnyhowContext;
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use validator::ValidateUrl;
+
+mod dry;
+mod mirror;
+mod tus;
+
+pub use dry::DryUpload;
+pub use mirror::MirrorUploader;
+pub use tus::Tus;
+
+pub struct UploadResponse {
+    /// Response body, which should be the download url
+    pub body: String,
+    /// Management token needed to delete the upload,
+    /// 0x0.st sends it in the `X-Token` header only for new uploads
+    pub token: Option<String>,
+    /// Download urls of the same archive on other hosts, only set by [`MirrorUploader`]
+    pub mirrors: Vec<String>,
+}
+
+/// The upload host rejected the archive for its size, its limit can be lower than ours
+#[derive(Debug, thiserror::Error)]
+#[error("Archive is too large for the upload host")]
+pub struct TooLarge;
+
+/// Rate limit the upload host reported with its last response
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Uploads left before the limit resets, `None` if the host didn't say
+    pub remaining: Option<u64>,
+    /// When the limit resets, `None` if the host didn't say
+    pub resets_at: Option<SystemTime>,
+}
+
+impl RateLimit {
+    /// Reads the `X-RateLimit-Remaining`, `X-RateLimit-Reset` and `Retry-After` headers,
+    /// `None` if the response has none of them
+    fn from_headers(headers: &reqwest::header::HeaderMap, now: SystemTime) -> Option<Self> {
+        let number = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+
+        // hosts send the reset either as a unix timestamp or as seconds from now
+        let reset = number("x-ratelimit-reset").map(|reset| {
+            if reset > RESET_TIMESTAMP_THRESHOLD {
+                UNIX_EPOCH + Duration::from_secs(reset)
+            } else {
+                now + Duration::from_secs(reset)
+            }
+        });
+        let retry_after = number("retry-after").map(|seconds| now + Duration::from_secs(seconds));
+        let limit = Self {
+            remaining: if retry_after.is_some() {
+                Some(0)
+            } else {
+                number("x-ratelimit-remaining")
+            },
+            resets_at: retry_after.or(reset),
+        };
+        (limit.remaining.is_some() || limit.resets_at.is_some()).then_some(limit)
+    }
+
+    /// How long until the host takes uploads again, `None` if it takes them now
+    pub fn wait(&self) -> Option<Duration> {
+        if self.remaining != Some(0) {
+            return None;
+        }
+        self.resets_at?.duration_since(SystemTime::now()).ok()
+    }
+
+    /// Whether only a few uploads are left before the host starts refusing them
+    pub fn is_low(&self) -> bool {
+        self.remaining
+            .is_some_and(|remaining| remaining > 0 && remaining <= LOW_RATE_LIMIT_REMAINING)
+    }
+}
+
+/// Uploads left at which the rate limit counts as nearly used up
+const LOW_RATE_LIMIT_REMAINING: u64 = 1;
+
+/// `X-RateLimit-Reset` values above this are unix timestamps rather than seconds from now
+const RESET_TIMESTAMP_THRESHOLD: u64 = 1_000_000_000;
+
+/// Host that archives are uploaded to
+#[async_trait]
+pub trait Uploader: Send + Sync {
+    /// Fails with [`TooLarge`] if the host rejects the archive for its size
+    async fn upload(&self, archive: PathBuf, expires_hours: u64) -> anyhow::Result<UploadResponse>;
+
+    /// Deletes an upload before it expires using its management token
+    async fn delete(&self, url: &str, token: &str) -> anyhow::Result<()>;
+
+    /// Rate limit the host reported last, `None` for hosts that don't report one
+    fn rate_limit(&self) -> Option<RateLimit> {
+        None
+    }
+
+    /// Caches of the uploader the housekeeping task prunes
+    fn expiring(&self) -> Vec<(&'static str, SharedExpiring)> {
+        Vec::new()
+    }
+
+    /// Link users download the archive named `file_name` from, given the url the host returned.
+    /// Each host decides how, only some of them honor a file name appended to the url
+    fn download_link(&self, url: &str, file_name: &str) -> String;
+}
+
+/// Uploads to 0x0.st or a self-hosted instance of it
+pub struct ZeroXZero {
+    upload_url: String,
+    client: reqwest::Client,
+    rate_limit: Mutex<Option<RateLimit>>,
+    /// Whether links end in the archive's file name
+    append_file_name: bool,
+}
+
+impl ZeroXZero {
+    pub fn new(
+        upload_url: String,
+        user_agent: &str,
+        append_file_name: bool,
+    ) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .gzip(true)
+            .brotli(true)
+            .build()
+            .context("Failed to create HTTP client")?;
+        Ok(Self {
+            upload_url,
+            client,
+            rate_limit: Mutex::new(None),
+            append_file_name,
+        })
+    }
+}
+
+#[async_trait]
+impl Uploader for ZeroXZero {
+    async fn upload(&self, archive: PathBuf, expires_hours: u64) -> anyhow::Result<UploadResponse> {
+        let form = reqwest::multipart::Form::new()
+            .text("expires", expires_hours.to_string())
+            .file("file", archive)
+            .await
+            .context("Failed to create upload form")?;
+
+        let response = self
+            .client
+            .post(&self.upload_url)
+            .multipart(form)
+            .send()
+            .await
+            .context("Failed to send request")?;
+
+        let rate_limit = RateLimit::from_headers(response.headers(), SystemTime::now());
+        if let Some(limit) = rate_limit {
+            if limit.remaining == Some(0) {
+                tracing::warn!("Upload host rate limit is used up: {:?}", limit);
+            } else {
+                tracing::debug!("Upload host rate limit: {:?}", limit);
+            }
+        }
+        *self.rate_limit.lock().unwrap() = rate_limit;
+
+        match response.status() {
+            reqwest::StatusCode::PAYLOAD_TOO_LARGE => return Err(TooLarge.into()),
+            reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                anyhow::bail!("Upload host is rate limiting uploads")
+            }
+            _ => {}
+        }
+
+        let token = response
+            .headers()
+            .get("X-Token")
+            .and_then(|token| token.to_str().ok())
+            .map(str::to_owned);
+
+        let body = response
+            .text()
+            .await
+            .context("Failed to get response text")?;
+
+        Ok(UploadResponse {
+            body,
+            token,
+            mirrors: Vec::new(),
+        })
+    }
+
+    async fn delete(&self, url: &str, token: &str) -> anyhow::Result<()> {
+        let form = reqwest::multipart::Form::new()
+            .text("token", token.to_owned())
+            .text("delete", "");
+
+        self.client
+            .post(url)
+            .multipart(form)
+            .send()
+            .await
+            .context("Failed to send request")?
+            .error_for_status()
+            .context("Upload host rejected the deletion")?;
+
+        Ok(())
+    }
+
+    fn rate_limit(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().unwrap()
+    }
+
+    /// 0x0.st renames the uploaded file,
+    /// appending the archive's file name to the download url gets the correct file name
+    fn download_link(&self, url: &str, file_name: &str) -> String {
+        if self.append_file_name {
+            format!("{}/{}", url, file_name)
+        } else {
+            url.to_owned()
+        }
+    }
+}
+
+/// Schemes a download url can have, `file` for [`DryUpload`]
+const DOWNLOAD_URL_SCHEMES: [&str; 3] = ["https", "http", "file"];
+
+/// Download url from the upload response body, `None` if the upload failed.
+/// Hosts sometimes print warnings around the url, the first line that is one is taken
+pub fn parse_download_url(body: &str) -> Option<String> {
+    body.lines()
+        .map(str::trim)
+        .find(|line| {
+            line.validate_url()
+                && line.split_once(':').is_some_and(|(scheme, _)| {
+                    DOWNLOAD_URL_SCHEMES
+                        .iter()
+                        .any(|allowed| scheme.eq_ignore_ascii_case(allowed))
+                })
+        })
+        .map(str::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limit_reads_headers() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            let mut headers = reqwest::header::HeaderMap::new();
+            for &(name, value) in pairs {
+                headers.insert(name, value.parse().unwrap());
+            }
+            headers
+        };
+
+        assert_eq!(RateLimit::from_headers(&headers(&[]), now), None);
+        assert_eq!(
+            RateLimit::from_headers(
+                &headers(&[("x-ratelimit-remaining", "3"), ("x-ratelimit-reset", "60")]),
+                now
+            ),
+            Some(RateLimit {
+                remaining: Some(3),
+                resets_at: Some(now + Duration::from_secs(60)),
+            })
+        );
+        assert_eq!(
+            RateLimit::from_headers(
+                &headers(&[
+                    ("X-RateLimit-Remaining", "0"),
+                    ("X-RateLimit-Reset", "1700000120")
+                ]),
+                now
+            ),
+            Some(RateLimit {
+                remaining: Some(0),
+                resets_at: Some(now + Duration::from_secs(120)),
+            })
+        );
+        assert_eq!(
+            RateLimit::from_headers(&headers(&[("retry-after", "30")]), now),
+            Some(RateLimit {
+                remaining: Some(0),
+                resets_at: Some(now + Duration::from_secs(30)),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_download_url_finds_the_url_line() {
+        assert_eq!(
+            parse_download_url("https://0x0.st/abcd.zip\n").as_deref(),
+            Some("https://0x0.st/abcd.zip")
+        );
+        assert_eq!(
+            parse_download_url("  https://0x0.st/abcd.zip  \r\n\r\n").as_deref(),
+            Some("https://0x0.st/abcd.zip")
+        );
+        assert_eq!(
+            parse_download_url(
+                "warning: file type is not recommended\nhttps://0x0.st/abcd.zip\nhttps://0x0.st/other.zip\n"
+            )
+            .as_deref(),
+            Some("https://0x0.st/abcd.zip")
+        );
+        assert_eq!(
+            parse_download_url("file:///tmp/dry-uploads/1-move.zip").as_deref(),
+            Some("file:///tmp/dry-uploads/1-move.zip")
+        );
+    }
+
+    #[test]
+    fn parse_download_url_rejects_bodies_without_one() {
+        assert_eq!(parse_download_url(""), None);
+        assert_eq!(parse_download_url("\n  \n"), None);
+        assert_eq!(parse_download_url("Segmentation fault: core dumped"), None);
+        assert_eq!(
+            parse_download_url("<html><body>502 Bad Gateway</body></html>"),
+            None
+        );
+        assert_eq!(parse_download_url("mailto:admin@0x0.st"), None);
+    }
+}
@@ -0,0 +1,149 @@
+use super::{UploadResponse, Uploader};
+use anyhow::Context as AnyhowContext;
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Stands in for the upload host with `DRY_UPLOAD=true`, each archive is copied to a local directory
+/// and its path returned as a `file://` url, so the rest of a pull runs as usual
+pub struct DryUpload {
+    dir: PathBuf,
+}
+
+impl DryUpload {
+    pub fn new(dir: PathBuf) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&dir).context(format!(
+            "Failed to create dry upload directory {}",
+            dir.display()
+        ))?;
+        // file urls need an absolute path
+        let dir = dir.canonicalize().context(format!(
+            "Failed to resolve dry upload directory {}",
+            dir.display()
+        ))?;
+        Ok(Self { dir })
+    }
+
+    /// Deletes the archives older than `ttl` every `interval` in the background,
+    /// so the directory doesn't grow for as long as the bot runs
+    pub fn spawn_eviction(&self, ttl: Duration, interval: Duration) {
+        let dir = self.dir.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                let dir = dir.clone();
+                let evicted =
+                    tokio::task::spawn_blocking(move || evict(&dir, ttl, SystemTime::now())).await;
+                if let Err(e) = evicted {
+                    tracing::warn!("Dry upload eviction task failed: {}", e);
+                }
+            }
+        });
+    }
+}
+
+/// Deletes the files in `dir` last modified more than `ttl` before `now`, returns how many
+fn evict(dir: &Path, ttl: Duration, now: SystemTime) -> usize {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::warn!("Failed to list dry uploads in {}: {}", dir.display(), e);
+            return 0;
+        }
+    };
+
+    let mut evicted = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let expired = entry.metadata().is_ok_and(|metadata| {
+            metadata.is_file()
+                && metadata
+                    .modified()
+                    .is_ok_and(|modified| now.duration_since(modified).unwrap_or_default() > ttl)
+        });
+        if !expired {
+            continue;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                tracing::info!("Evicted dry upload {}", path.display());
+                evicted += 1;
+            }
+            Err(e) => tracing::warn!("Failed to evict dry upload {}: {}", path.display(), e),
+        }
+    }
+    evicted
+}
+
+#[async_trait]
+impl Uploader for DryUpload {
+    async fn upload(
+        &self,
+        archive: PathBuf,
+        _expires_hours: u64,
+    ) -> anyhow::Result<UploadResponse> {
+        let file_name = archive
+            .file_name()
+            .context("Archive has no file name")?
+            .to_string_lossy();
+        // pulls of the same move have the same archive name
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = self.dir.join(format!("{}-{}", millis, file_name));
+        tokio::fs::copy(&archive, &path)
+            .await
+            .context(format!("Failed to copy archive to {}", path.display()))?;
+
+        let url = reqwest::Url::from_file_path(&path)
+            .map_err(|()| anyhow::anyhow!("Invalid dry upload path {}", path.display()))?;
+        tracing::info!("Dry upload of {} to {}", archive.display(), path.display());
+        Ok(UploadResponse {
+            body: url.to_string(),
+            token: Some(url.to_string()),
+            mirrors: Vec::new(),
+        })
+    }
+
+    async fn delete(&self, url: &str, _token: &str) -> anyhow::Result<()> {
+        let path = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .context("Not a dry upload url")?;
+        tokio::fs::remove_file(&path)
+            .await
+            .context(format!("Failed to remove {}", path.display()))
+    }
+
+    fn download_link(&self, url: &str, _file_name: &str) -> String {
+        url.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evict_removes_only_expired_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old.zip");
+        let new = dir.path().join("new.zip");
+        std::fs::write(&old, b"old").unwrap();
+        std::fs::write(&new, b"new").unwrap();
+
+        let now = SystemTime::now();
+        std::fs::File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(now - Duration::from_secs(2 * 60 * 60))
+            .unwrap();
+
+        assert_eq!(evict(dir.path(), Duration::from_secs(60 * 60), now), 1);
+        assert!(!old.exists());
+        assert!(new.exists());
+    }
+}
@@ -0,0 +1,147 @@
+use super::{RateLimit, UploadResponse, Uploader, parse_download_url};
+use crate::housekeeping::{Expiring, SharedExpiring};
+use async_trait::async_trait;
+use poise::futures_util::future::join_all;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Uploads each archive to several hosts at once, so a link is left if one host purges early.
+/// The first host that succeeds gives the main link, the others are returned as mirrors
+pub struct MirrorUploader {
+    /// Hosts with the name they're logged as
+    hosts: Vec<(String, Box<dyn Uploader>)>,
+    /// Host of each url uploaded so far, so links and deletions go to the host that made them
+    url_hosts: Arc<Mutex<UrlHosts>>,
+}
+
+impl MirrorUploader {
+    pub fn new(hosts: Vec<(String, Box<dyn Uploader>)>) -> Self {
+        Self {
+            hosts,
+            url_hosts: Arc::new(Mutex::new(UrlHosts::default())),
+        }
+    }
+
+    /// Host that `url` was uploaded to, the first host for urls that expired or are from before a restart
+    fn host(&self, url: &str) -> &dyn Uploader {
+        let index = self.url_hosts.lock().unwrap().get(url);
+        self.hosts[index.unwrap_or(0)].1.as_ref()
+    }
+}
+
+/// Longest a url's host is kept, no host keeps uploads longer than a year
+const MAX_URL_HOST_HOURS: u64 = 365 * 24;
+
+/// Host index of each uploaded url until the upload expires, there's nothing left to delete after
+#[derive(Default)]
+struct UrlHosts {
+    hosts: HashMap<String, (usize, Instant)>,
+}
+
+impl UrlHosts {
+    fn get(&self, url: &str) -> Option<usize> {
+        self.hosts.get(url).map(|(index, _)| *index)
+    }
+
+    fn insert(&mut self, url: String, index: usize, expires_hours: u64) {
+        let hours = expires_hours.min(MAX_URL_HOST_HOURS);
+        let expires_at = Instant::now() + Duration::from_secs(hours * 60 * 60);
+        self.hosts.insert(url, (index, expires_at));
+    }
+}
+
+impl Expiring for UrlHosts {
+    fn prune(&mut self) -> usize {
+        let before = self.hosts.len();
+        let now = Instant::now();
+        self.hosts.retain(|_, (_, expires_at)| *expires_at > now);
+        before - self.hosts.len()
+    }
+}
+
+#[async_trait]
+impl Uploader for MirrorUploader {
+    async fn upload(&self, archive: PathBuf, expires_hours: u64) -> anyhow::Result<UploadResponse> {
+        let results = join_all(
+            self.hosts
+                .iter()
+                .map(|(_, host)| host.upload(archive.clone(), expires_hours)),
+        )
+        .await;
+
+        let mut primary: Option<UploadResponse> = None;
+        let mut mirrors = Vec::new();
+        let mut first_error = None;
+        let mut first_invalid = None;
+        let mut url_hosts = Vec::new();
+        for (index, result) in results.into_iter().enumerate() {
+            let name = &self.hosts[index].0;
+            match result {
+                Ok(response) => match parse_download_url(&response.body) {
+                    Some(url) => {
+                        url_hosts.push((url.clone(), index));
+                        if primary.is_none() {
+                            primary = Some(response);
+                        } else {
+                            mirrors.push(url);
+                        }
+                    }
+                    None => {
+                        tracing::warn!(
+                            "Mirror {} didn't return a download url. Response:\n{}",
+                            name,
+                            response.body
+                        );
+                        first_invalid.get_or_insert(response);
+                    }
+                },
+                Err(e) => {
+                    tracing::warn!("Failed to upload to mirror {}: {:#}", name, e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        {
+            let mut known = self.url_hosts.lock().unwrap();
+            for (url, index) in url_hosts {
+                known.insert(url, index, expires_hours);
+            }
+        }
+
+        match (primary, first_error, first_invalid) {
+            (Some(mut response), _, _) => {
+                response.mirrors = mirrors;
+                Ok(response)
+            }
+            (None, Some(e), _) => Err(e),
+            (None, None, Some(response)) => Ok(response),
+            (None, None, None) => anyhow::bail!("No upload hosts are configured"),
+        }
+    }
+
+    /// Deletes only the copy at `url`, the mirrors' tokens aren't kept
+    async fn delete(&self, url: &str, token: &str) -> anyhow::Result<()> {
+        self.host(url).delete(url, token).await
+    }
+
+    /// Only limited when every host reports a limit, then the first host that takes uploads now,
+    /// nearly used up or not, or the host that takes them again first if none does
+    fn rate_limit(&self) -> Option<RateLimit> {
+        let limits: Option<Vec<RateLimit>> = self
+            .hosts
+            .iter()
+            .map(|(_, host)| host.rate_limit())
+            .collect();
+        limits?.into_iter().min_by_key(|limit| limit.wait())
+    }
+
+    fn expiring(&self) -> Vec<(&'static str, SharedExpiring)> {
+        vec![("mirror url hosts", self.url_hosts.clone())]
+    }
+
+    fn download_link(&self, url: &str, file_name: &str) -> String {
+        self.host(url).download_link(url, file_name)
+    }
+}
@@ -0,0 +1,198 @@
+use super::{TooLarge, UploadResponse, Uploader};
+use anyhow::Context as AnyhowContext;
+use async_trait::async_trait;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, LOCATION};
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncSeekExt;
+
+const TUS_VERSION: &str = "1.0.0";
+const MAX_UPLOAD_ATTEMPTS: u32 = 5;
+/// Wait before the first retry of an interrupted upload, doubled for each retry after it
+const RETRY_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Uploads to a tus server such as tusd, resuming interrupted uploads where they stopped
+pub struct Tus {
+    endpoint: String,
+    client: reqwest::Client,
+}
+
+impl Tus {
+    pub fn new(endpoint: String, user_agent: &str) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .build()
+            .context("Failed to create HTTP client")?;
+        Ok(Self { endpoint, client })
+    }
+
+    /// Creates an empty upload of `length` bytes and returns its url
+    async fn create(&self, file_name: &str, length: u64) -> anyhow::Result<String> {
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .header("Tus-Resumable", TUS_VERSION)
+            .header("Upload-Length", length)
+            .header(
+                "Upload-Metadata",
+                format!("filename {}", BASE64_STANDARD.encode(file_name)),
+            )
+            .send()
+            .await
+            .context("Failed to send request")?;
+        // servers answer uploads over their Tus-Max-Size with 413
+        if response.status() == reqwest::StatusCode::PAYLOAD_TOO_LARGE {
+            return Err(TooLarge.into());
+        }
+        let response = response
+            .error_for_status()
+            .context("Upload host rejected the upload")?;
+
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .context("Upload host didn't return an upload url")?;
+
+        // the location is allowed to be relative to the endpoint
+        let url = reqwest::Url::parse(&self.endpoint)
+            .and_then(|endpoint| endpoint.join(location))
+            .context("Upload host returned an invalid upload url")?;
+        Ok(url.into())
+    }
+
+    /// Sends the rest of the archive starting at `offset`
+    async fn send_from(
+        &self,
+        upload_url: &str,
+        archive: &Path,
+        offset: u64,
+        length: u64,
+    ) -> anyhow::Result<()> {
+        let mut file = tokio::fs::File::open(archive)
+            .await
+            .context("Failed to open archive")?;
+        file.seek(SeekFrom::Start(offset))
+            .await
+            .context("Failed to seek archive")?;
+
+        self.client
+            .patch(upload_url)
+            .header("Tus-Resumable", TUS_VERSION)
+            .header("Upload-Offset", offset)
+            .header(CONTENT_TYPE, "application/offset+octet-stream")
+            .header(CONTENT_LENGTH, length - offset)
+            .body(file)
+            .send()
+            .await
+            .context("Failed to send request")?
+            .error_for_status()
+            .context("Upload host rejected the archive data")?;
+
+        Ok(())
+    }
+
+    /// Bytes the server already received of the upload at `upload_url`
+    async fn offset(&self, upload_url: &str) -> anyhow::Result<u64> {
+        let response = self
+            .client
+            .head(upload_url)
+            .header("Tus-Resumable", TUS_VERSION)
+            .send()
+            .await
+            .context("Failed to send request")?
+            .error_for_status()
+            .context("Upload host lost the interrupted upload")?;
+
+        let offset = response
+            .headers()
+            .get("Upload-Offset")
+            .and_then(|offset| offset.to_str().ok())
+            .and_then(|offset| offset.parse().ok())
+            .context("Upload host didn't return the upload offset")?;
+        Ok(offset)
+    }
+}
+
+#[async_trait]
+impl Uploader for Tus {
+    /// tus has no per upload expiry, links expire however the server is set up
+    async fn upload(
+        &self,
+        archive: PathBuf,
+        _expires_hours: u64,
+    ) -> anyhow::Result<UploadResponse> {
+        let length = tokio::fs::metadata(&archive)
+            .await
+            .context("Failed to read archive size")?
+            .len();
+        let file_name = archive
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let upload_url = self.create(&file_name, length).await?;
+
+        let mut attempt = 1;
+        let mut result = self.send_from(&upload_url, &archive, 0, length).await;
+        while let Err(e) = result {
+            if attempt == MAX_UPLOAD_ATTEMPTS {
+                return Err(e);
+            }
+            // a short outage would use up every attempt at once without a wait
+            let backoff = RETRY_BACKOFF * 2u32.pow(attempt - 1);
+            tracing::warn!(
+                "Upload to {} interrupted, retrying in {}s: {:#}",
+                upload_url,
+                backoff.as_secs(),
+                e
+            );
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+
+            // a failed offset check is a failed attempt like a failed send
+            result = match self.offset(&upload_url).await {
+                Ok(offset) if offset >= length => Ok(()),
+                Ok(offset) => {
+                    tracing::info!(
+                        "Resuming upload to {} at {} of {} bytes",
+                        upload_url,
+                        offset,
+                        length
+                    );
+                    self.send_from(&upload_url, &archive, offset, length).await
+                }
+                Err(e) => Err(e),
+            };
+        }
+
+        // knowing the upload url is all it takes to terminate the upload
+        Ok(UploadResponse {
+            token: Some(upload_url.clone()),
+            body: upload_url,
+            mirrors: Vec::new(),
+        })
+    }
+
+    /// Terminates the upload, the token is the upload url handed out by `upload`
+    async fn delete(&self, url: &str, _token: &str) -> anyhow::Result<()> {
+        self.client
+            .delete(url)
+            .header("Tus-Resumable", TUS_VERSION)
+            .send()
+            .await
+            .context("Failed to send request")?
+            .error_for_status()
+            .context("Upload host rejected the deletion")?;
+
+        Ok(())
+    }
+
+    /// tus servers serve the upload url itself, with the file name from the upload metadata
+    fn download_link(&self, url: &str, _file_name: &str) -> String {
+        url.to_owned()
+    }
+}
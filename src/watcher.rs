@@ -0,0 +1,83 @@
+use crate::moves;
+use anyhow::Context as AnyhowContext;
+use arc_swap::ArcSwap;
+use notify::{RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Quiet time after the last change before the file is read, editors save files in several steps
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Reloads the move list from `path` into `move_list` in the background whenever the file changes
+pub fn spawn(path: PathBuf, move_list: Arc<ArcSwap<Vec<String>>>) -> anyhow::Result<()> {
+    // the directory is watched since many editors replace the file instead of writing to it
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_owned();
+    let file_name = path
+        .file_name()
+        .context("Move list path has no file name")?
+        .to_owned();
+
+    let (sender, mut changes) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event)
+                if !event.kind.is_access()
+                    && event
+                        .paths
+                        .iter()
+                        .any(|path| path.file_name() == Some(&file_name)) =>
+            {
+                // only fails once the reload task is gone
+                let _ = sender.send(());
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to watch the move list: {}", e),
+        })
+        .context("Failed to create file watcher")?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .context(format!("Failed to watch {}", dir.display()))?;
+    tracing::info!("Watching the move list {} for changes", path.display());
+
+    tokio::spawn(async move {
+        // the watcher stops when it's dropped
+        let _watcher = watcher;
+        while changes.recv().await.is_some() {
+            while let Ok(Some(())) = tokio::time::timeout(DEBOUNCE, changes.recv()).await {}
+            reload(&path, &move_list);
+        }
+    });
+    Ok(())
+}
+
+/// Swaps in the move list at `path` if it changed, keeping the loaded one if it can't be read or is empty
+fn reload(path: &Path, move_list: &ArcSwap<Vec<String>>) {
+    let loaded = move_list.load();
+    match moves::load(path) {
+        Ok(moves) if moves.is_empty() => tracing::warn!(
+            "The move list {} is now empty, keeping the {} moves loaded before",
+            path.display(),
+            loaded.len()
+        ),
+        Ok(moves) if moves == **loaded => {}
+        Ok(moves) => {
+            tracing::info!(
+                "Reloaded the move list {}, {} moves instead of {}",
+                path.display(),
+                moves.len(),
+                loaded.len()
+            );
+            move_list.store(Arc::new(moves));
+        }
+        Err(e) => tracing::warn!(
+            "Failed to reload the move list {}, keeping the moves loaded before: {}",
+            path.display(),
+            e
+        ),
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::Context as AnyhowContext;
+use poise::serenity_prelude::Timestamp;
+use std::time::Duration;
+
+const COMPLETION_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Finished pull reported to the completion webhook
+pub struct Completion {
+    pub move_name: String,
+    pub submitter: String,
+    pub link: String,
+    pub size_bytes: u64,
+    pub clips: usize,
+}
+
+/// Posts `completion` to `url` in the background, failures are only logged
+pub fn notify_completion(url: String, user_agent: String, completion: Completion) {
+    tokio::spawn(async move {
+        if let Err(e) = post_completion(&url, &user_agent, completion).await {
+            tracing::warn!("Failed to notify the completion webhook: {:#}", e);
+        }
+    });
+}
+
+async fn post_completion(
+    url: &str,
+    user_agent: &str,
+    completion: Completion,
+) -> anyhow::Result<()> {
+    let payload = serde_json::json!({
+        "move_name": completion.move_name,
+        "submitter": completion.submitter,
+        "link": completion.link,
+        "size_bytes": completion.size_bytes,
+        "clips": completion.clips,
+        "timestamp": Timestamp::now(),
+    });
+
+    reqwest::Client::builder()
+        .user_agent(user_agent)
+        .timeout(COMPLETION_WEBHOOK_TIMEOUT)
+        .build()
+        .context("Failed to create HTTP client")?
+        .post(url)
+        .json(&payload)
+        .send()
+        .await
+        .context("Failed to send request")?
+        .error_for_status()
+        .context("Webhook rejected the notification")?;
+
+    Ok(())
+}